@@ -0,0 +1,40 @@
+fn main() -> anyhow::Result<()> {
+    let inp = gaborator::wav::read_wav("input.wav")?;
+    if inp.channels.len() != 1 {
+        anyhow::bail!("input.wav should be mono");
+    }
+    let sr = inp.sample_rate;
+    let samples = &inp.channels[0];
+
+    let g = gaborator::Gaborator::new(&gaborator::GaboratorParams::for_sample_rate(sr as f64, 50.0, 440.0, 48));
+
+    let mut coefs = gaborator::Coefs::new(&g);
+    g.analyze(samples, 0, &mut coefs);
+
+    let mut lossless = Vec::new();
+    coefs.save(&mut lossless)?;
+
+    for settings in [
+        gaborator::codec::LossyParams { threshold_db: 40.0, magnitude_bits: 5, phase_bits: 4 },
+        gaborator::codec::LossyParams::default(),
+        gaborator::codec::LossyParams { threshold_db: 80.0, magnitude_bits: 10, phase_bits: 8 },
+    ] {
+        let mut encoded = Vec::new();
+        coefs.save_lossy(&g, &mut encoded, &settings)?;
+
+        let decoded = gaborator::Coefs::load_lossy(&encoded[..], &g)?;
+        let mut output = vec![0.0; samples.len()];
+        g.synthesize(&decoded, 0, &mut output);
+
+        println!(
+            "{:?}: {} bytes ({:.1}% of lossless, {:.1} kbit/s), SNR {:.1} dB",
+            settings,
+            encoded.len(),
+            100.0 * encoded.len() as f64 / lossless.len() as f64,
+            encoded.len() as f64 * 8.0 / (samples.len() as f64 / sr as f64) / 1000.0,
+            gaborator::codec::snr_db(samples, &output),
+        );
+    }
+
+    Ok(())
+}
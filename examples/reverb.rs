@@ -1,49 +1,71 @@
 fn main() -> anyhow::Result<()> {
-    let inp = hound::WavReader::open("input.wav")?;
-    if inp.spec().channels != 1 {
-        anyhow::bail!("input.wav should be mono");
-    }
-    let sr = inp.spec().sample_rate;
-    if !(44000..=49000).contains(&sr) {
-        anyhow::bail!("Input sample rate should be around 48000")
-    }
-    let mut samples : Vec<f32> = if inp.spec().sample_format == hound::SampleFormat::Float {
-        inp.into_samples::<f32>().collect::<Result<Vec<_>,_>>()?
-    } else {
-        inp.into_samples::<i32>().map(|x|x.map(|s|s as f32 / 32768.00)).collect::<Result<Vec<_>,_>>()?
-    };
-
-    let g = gaborator::Gaborator::new(&gaborator::GaboratorParams {
-        bands_per_octave: 256,
-        ff_min: 200.0 / (sr as f64),
-        ff_ref: 440.0 / (sr as f64),
-        overlap: 0.7,
-    });
-
-    let mut coefs = gaborator::Coefs::new(&g);
-
-    g.analyze(&samples, 0, &mut coefs);
-
-    coefs.process(-100000, 100000, -100000, 10000000000,
-        |_meta,coef| {
-            let (magn, mut _phase) = num_complex::Complex::new(coef.re, coef.im).to_polar();
-            _phase *= 100000.0; // lousy way to simulate randomness without reaching for `rand` crate.
-            let q = num_complex::Complex::from_polar(magn, _phase);
-            coef.re = q.re;
-            coef.im = q.im;
-        }
-    );
+    let mut inp = hound::WavReader::open("input.wav")?;
+    let spec = inp.spec();
+    let sr = spec.sample_rate;
+    let n_channels = spec.channels as usize;
+    let int_scale = gaborator::wav::int_sample_scale(spec.bits_per_sample);
+
+    let g = gaborator::Gaborator::new(&gaborator::GaboratorParams::for_sample_rate(sr as f64, 200.0, 440.0, 256));
 
-    g.synthesize(&coefs, 0, &mut samples);
+    let mut processors: Vec<_> = (0..n_channels).map(|_| gaborator::StreamingProcessor::new(&g, 0)).collect();
+    let latency = processors[0].latency();
 
     let mut outp = hound::WavWriter::create("output.wav", hound::WavSpec {
-        channels: 1,
+        channels: spec.channels,
         sample_rate: sr,
         bits_per_sample: 32,
         sample_format: hound::SampleFormat::Float,
     })?;
 
-    samples.into_iter().try_for_each(move |x| -> anyhow::Result<()> {outp.write_sample(x)?; Ok(()) } )?;
+    const BUFSIZE : usize = 4096;
+
+    // Output lags behind input; skip the leading silence and feed silence at the end to get the tail.
+    let mut to_skip = latency;
+    let mut tail_remaining = latency;
+    let mut input = vec![Vec::with_capacity(BUFSIZE); n_channels];
+    let mut output = vec![vec![0.0; BUFSIZE]; n_channels];
+
+    loop {
+        let interleaved : Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => {
+                inp.samples::<f32>().take(BUFSIZE * n_channels).collect::<Result<Vec<_>,_>>()?
+            }
+            hound::SampleFormat::Int => {
+                inp.samples::<i32>().take(BUFSIZE * n_channels).map(|x|x.map(|s|s as f32 * int_scale)).collect::<Result<Vec<_>,_>>()?
+            }
+        };
+        let mut n = interleaved.len() / n_channels;
+        for (c, samples) in input.iter_mut().enumerate() {
+            samples.clear();
+            samples.extend(interleaved.iter().skip(c).step_by(n_channels).take(n));
+        }
+        if n == 0 {
+            if tail_remaining == 0 { break; }
+            n = tail_remaining.min(BUFSIZE);
+            for samples in &mut input {
+                samples.resize(n, 0.0);
+            }
+            tail_remaining -= n;
+        }
+
+        for ((processor, samples), output) in processors.iter_mut().zip(&input).zip(&mut output) {
+            processor.process_block(samples, &mut output[..n], |_meta,coef| {
+                let (magn, mut _phase) = coef.to_polar();
+                _phase *= 100000.0; // lousy way to simulate randomness without reaching for `rand` crate.
+                *coef = gaborator::Coef::from_polar(magn, _phase);
+            });
+        }
+
+        let skip = to_skip.min(n);
+        to_skip -= skip;
+        for i in skip..n {
+            for output in &output {
+                outp.write_sample(output[i])?;
+            }
+        }
+    }
+
+    outp.finalize()?;
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,43 @@
+use gaborator::transcribe::{transcribe, TranscribeParams};
+
+fn main() -> anyhow::Result<()> {
+    eprintln!("Reading WAV data from stdin, writing MIDI file to stdout");
+
+    let si = std::io::stdin();
+    let si = si.lock();
+    let si = std::io::BufReader::new(si);
+    let audio = gaborator::wav::read_wav_from(si)?;
+    let sr = audio.sample_rate as f64;
+
+    // Mix all channels
+    let mut signal = vec![0.0f32; audio.len()];
+    for channel in &audio.channels {
+        for (s, x) in signal.iter_mut().zip(channel) {
+            *s += x / audio.channels.len() as f32;
+        }
+    }
+
+    let g = gaborator::Gaborator::with_sample_rate(
+        &gaborator::GaboratorParams::for_sample_rate(sr, 20.0, 440.0, 24),
+        sr,
+    );
+    let mut coefs = gaborator::Coefs::new(&g);
+    g.analyze(&signal, 0, &mut coefs);
+
+    let notes = transcribe(&g, &mut coefs, 0..signal.len() as i64, &TranscribeParams::default())?;
+    for n in &notes {
+        eprintln!(
+            "{:8.3} {:8.3} {:>4} {:+4.0} {:3}",
+            n.onset as f64 / sr,
+            n.offset as f64 / sr,
+            n.note.name(),
+            n.note.cents,
+            n.velocity,
+        );
+    }
+
+    let so = std::io::stdout();
+    let so = so.lock();
+    gaborator::midi::write_midi(std::io::BufWriter::new(so), &notes, sr)?;
+    Ok(())
+}
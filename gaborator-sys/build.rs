@@ -8,4 +8,5 @@ fn main() {
     println!("cargo:rerun-if-changed=src/lib.rs");
     println!("cargo:rerun-if-changed=src/gabbridge.cc");
     println!("cargo:rerun-if-changed=src/gabbridge.h");
+    println!("cargo:rerun-if-changed=gaborator");
 }
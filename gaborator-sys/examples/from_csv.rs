@@ -6,9 +6,9 @@ fn main() -> anyhow::Result<()> {
         ff_min: 200.0 / (sr as f64),
         ff_ref: 440.0 / (sr as f64),
         overlap: 0.7,
-    });
+    })?;
 
-    let mut coefs = gaborator_sys::create_coefs(&g);
+    let mut coefs = gaborator_sys::create_coefs(&g)?;
 
     let mut max_sample_number = 0;
 
@@ -52,11 +52,11 @@ fn main() -> anyhow::Result<()> {
                 }
             }
         )),
-    );
+    )?;
 
     let mut samples: Vec<f32> = vec![0.0; max_sample_number as usize + 100];
 
-    gaborator_sys::synthesize(&g, &coefs, 0, &mut samples);
+    gaborator_sys::synthesize(&g, &coefs, 0, &mut samples)?;
 
     let mut outp = hound::WavWriter::create(
         "output.wav",
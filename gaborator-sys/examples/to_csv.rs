@@ -7,10 +7,11 @@ fn main() -> anyhow::Result<()> {
     if !(44000..=49000).contains(&sr) {
         anyhow::bail!("Input sample rate should be around 48000")
     }
+    let scale = 1.0 / (1u64 << (inp.spec().bits_per_sample - 1)) as f32;
     let samples : Vec<f32> = if inp.spec().sample_format == hound::SampleFormat::Float {
         inp.into_samples::<f32>().collect::<Result<Vec<_>,_>>()?
     } else {
-        inp.into_samples::<i32>().map(|x|x.map(|s|s as f32 / 32768.00)).collect::<Result<Vec<_>,_>>()?
+        inp.into_samples::<i32>().map(|x|x.map(|s|s as f32 * scale)).collect::<Result<Vec<_>,_>>()?
     };
 
     let g = gaborator_sys::new_analyzer(&gaborator_sys::Params {
@@ -18,11 +19,11 @@ fn main() -> anyhow::Result<()> {
         ff_min: 200.0 / (sr as f64),
         ff_ref: 440.0 / (sr as f64),
         overlap: 0.7,
-    });
+    })?;
 
-    let mut coefs = gaborator_sys::create_coefs(&g);
+    let mut coefs = gaborator_sys::create_coefs(&g)?;
 
-    gaborator_sys::analyze(&g, &samples, 0, coefs.pin_mut());
+    gaborator_sys::analyze(&g, &samples, 0, coefs.pin_mut())?;
 
 
     let num_samples = samples.len();
@@ -33,7 +34,7 @@ fn main() -> anyhow::Result<()> {
 
     const BUF_SIZE : usize = 24000;
 
-    for i in 0..(num_samples as usize + 1) / BUF_SIZE {
+    for i in 0..(num_samples + 1) / BUF_SIZE {
         use std::io::Write;
 
         gaborator_sys::process(
@@ -501,6 +501,9 @@ struct oct_coefs: public refcounted {
             clear();
     }
     oct_coefs(const oct_coefs &) = delete;
+    // gaborator-rs patch: allocate through pod_alloc (see patches/pod_alloc.patch)
+    static void *operator new(size_t size) { return pod_alloc(size); }
+    static void operator delete(void *p) { pod_free(p); }
     uint64_t estimate_memory_usage() const {
         return zmeta.total_size * sizeof(C) + sizeof(*this);
     }
@@ -1050,10 +1053,11 @@ struct buffers {
         def(sftsize_max * sizeof(C)); // 4
         def(fftsize_max * sizeof(T)); // 5
         assert(n <= maxbufs);
-        data = ::operator new(offset[n]);
+        // gaborator-rs patch: allocate through pod_alloc (see patches/pod_alloc.patch)
+        data = pod_alloc(offset[n]);
     }
     ~buffers() {
-        ::operator delete(data);
+        pod_free(data);
     }
     void def(size_t size) {
         size_t o = offset[n++];
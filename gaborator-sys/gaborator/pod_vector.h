@@ -15,6 +15,15 @@
 
 namespace gaborator {
 
+// gaborator-rs patch (see patches/pod_alloc.patch): allocation hooks
+// for raw memory used by pod_vector and coefficient slices.
+// Implemented by gaborator-sys, which can recycle the memory in
+// real-time mode.  They throw std::bad_alloc on failure, like
+// ::operator new.
+
+void *pod_alloc(size_t size);
+void pod_free(void *p);
+
 // A vector for storing Plain Old Data.  This is similar to a
 // std::vector, except that it does not zero-initialize elements,
 // and that it guarantees that data() returns a non-NULL pointer
@@ -29,7 +38,7 @@ struct pod_vector {
     }
     explicit pod_vector(size_t size_) {
         // Allocate raw uninitialized memory
-        b = static_cast<T *>(::operator new(size_ * sizeof(T)));
+        b = static_cast<T *>(pod_alloc(size_ * sizeof(T)));
         e = b + size_;
     }
     ~pod_vector()
@@ -53,7 +62,7 @@ struct pod_vector {
     void resize(size_t new_size) {
         if (new_size == size())
             return;
-        T *n = static_cast<T *>(::operator new(new_size * sizeof(T)));
+        T *n = static_cast<T *>(pod_alloc(new_size * sizeof(T)));
         size_t ncopy = std::min(size(), new_size);
         std::copy(b, b + ncopy, n);
         _free();
@@ -63,7 +72,7 @@ struct pod_vector {
     pod_vector(const pod_vector &a)
 
     {
-        b = new T[a.size()];
+        b = static_cast<T *>(pod_alloc(a.size() * sizeof(T)));
         e = b + a.size();
         std::copy(a.b, a.e, b);
         //if (size()) fprintf(stderr, "pod_vector cc %d\n", (int)size());
@@ -84,7 +93,7 @@ struct pod_vector {
         if (&a == this)
             return *this;
         _free();
-        b = new T[a.size()];
+        b = static_cast<T *>(pod_alloc(a.size() * sizeof(T)));
         e = b + a.size();
         std::copy(a.b, a.e, b);
         //if (size()) fprintf(stderr, "pod_vector =  %d\n", (int)size());
@@ -103,7 +112,7 @@ struct pod_vector {
 private:
     void _free() {
         // Free as raw uninitialized memory
-        ::operator delete(b);
+        pod_free(b);
     }
 private:
     T *b;
@@ -0,0 +1,115 @@
+use crate::Coef;
+
+impl Coef {
+    /// Create a coefficient from real and imaginary parts
+    pub const fn new(re: f32, im: f32) -> Self {
+        Coef { re, im }
+    }
+
+    /// Create a coefficient from magnitude and phase (in radians)
+    pub fn from_polar(norm: f32, arg: f32) -> Self {
+        let (sin, cos) = arg.sin_cos();
+        Coef { re: norm * cos, im: norm * sin }
+    }
+
+    /// Magnitude (absolute value) of the coefficient
+    pub fn norm(self) -> f32 {
+        self.re.hypot(self.im)
+    }
+
+    /// Squared magnitude of the coefficient, which is cheaper to calculate than `norm`
+    pub fn norm_sqr(self) -> f32 {
+        self.re * self.re + self.im * self.im
+    }
+
+    /// Phase (argument) of the coefficient in radians, from -π to π
+    pub fn arg(self) -> f32 {
+        self.im.atan2(self.re)
+    }
+
+    /// Magnitude and phase of the coefficient
+    pub fn to_polar(self) -> (f32, f32) {
+        (self.norm(), self.arg())
+    }
+
+    /// Complex conjugate of the coefficient
+    pub fn conj(self) -> Self {
+        Coef { re: self.re, im: -self.im }
+    }
+}
+
+impl std::ops::Add for Coef {
+    type Output = Coef;
+    fn add(self, rhs: Coef) -> Coef {
+        Coef { re: self.re + rhs.re, im: self.im + rhs.im }
+    }
+}
+
+impl std::ops::Sub for Coef {
+    type Output = Coef;
+    fn sub(self, rhs: Coef) -> Coef {
+        Coef { re: self.re - rhs.re, im: self.im - rhs.im }
+    }
+}
+
+impl std::ops::Mul for Coef {
+    type Output = Coef;
+    fn mul(self, rhs: Coef) -> Coef {
+        Coef {
+            re: self.re * rhs.re - self.im * rhs.im,
+            im: self.re * rhs.im + self.im * rhs.re,
+        }
+    }
+}
+
+impl std::ops::Mul<f32> for Coef {
+    type Output = Coef;
+    fn mul(self, rhs: f32) -> Coef {
+        Coef { re: self.re * rhs, im: self.im * rhs }
+    }
+}
+
+impl std::ops::Neg for Coef {
+    type Output = Coef;
+    fn neg(self) -> Coef {
+        Coef { re: -self.re, im: -self.im }
+    }
+}
+
+impl std::ops::AddAssign for Coef {
+    fn add_assign(&mut self, rhs: Coef) {
+        *self = *self + rhs;
+    }
+}
+
+impl std::ops::SubAssign for Coef {
+    fn sub_assign(&mut self, rhs: Coef) {
+        *self = *self - rhs;
+    }
+}
+
+impl std::ops::MulAssign for Coef {
+    fn mul_assign(&mut self, rhs: Coef) {
+        *self = *self * rhs;
+    }
+}
+
+impl std::ops::MulAssign<f32> for Coef {
+    fn mul_assign(&mut self, rhs: f32) {
+        *self = *self * rhs;
+    }
+}
+
+#[cfg(feature = "num-complex")]
+impl From<num_complex::Complex32> for Coef {
+    fn from(c: num_complex::Complex32) -> Self {
+        Coef { re: c.re, im: c.im }
+    }
+}
+
+#[cfg(feature = "num-complex")]
+impl From<Coef> for num_complex::Complex32 {
+    fn from(c: Coef) -> Self {
+        num_complex::Complex32::new(c.re, c.im)
+    }
+}
@@ -3,13 +3,98 @@
 
 namespace gabbridge {
 
+// Each block starts with a header storing its capacity (including the header)
+// and, while the block is in a recycler, a pointer to the next free block.
+struct BlockHeader {
+    size_t capacity;
+    void *next;
+};
+static const size_t header_size = 16;
+static_assert(sizeof(BlockHeader) <= header_size, "BlockHeader should fit into header_size");
+
+static thread_local Recycler *current_recycler = nullptr;
+
+static int floor_log2(size_t x)
+{
+    int r = 0;
+    while (x >>= 1)
+        r++;
+    return r;
+}
+
+static int ceil_log2(size_t x)
+{
+    int r = floor_log2(x);
+    return ((size_t)1 << r) < x ? r + 1 : r;
+}
+
+Recycler::Recycler(): previous(nullptr)
+{
+    for (int i = 0; i < n_classes; i++)
+        heads[i] = nullptr;
+}
+
+Recycler::~Recycler()
+{
+    for (int i = 0; i < n_classes; i++) {
+        while (heads[i]) {
+            BlockHeader *h = (BlockHeader *)heads[i];
+            heads[i] = h->next;
+            rust_dealloc((uint8_t *)h, h->capacity);
+        }
+    }
+}
+
+void *Recycler::take(size_t size)
+{
+    for (int i = ceil_log2(size + header_size); i < n_classes; i++) {
+        if (heads[i]) {
+            BlockHeader *h = (BlockHeader *)heads[i];
+            heads[i] = h->next;
+            return h;
+        }
+    }
+    return nullptr;
+}
+
+void Recycler::give(void *block, size_t capacity)
+{
+    BlockHeader *h = (BlockHeader *)block;
+    int i = floor_log2(capacity);
+    h->next = heads[i];
+    heads[i] = h;
+}
+
+std::unique_ptr<Recycler> new_recycler()
+{
+    return std::unique_ptr<Recycler>(new Recycler());
+}
+
+void activate_recycler(Recycler &r)
+{
+    r.previous = current_recycler;
+    current_recycler = &r;
+}
+
+void deactivate_recycler(Recycler &r) { current_recycler = r.previous; }
+
+static gaborator::parameters convert_params(const Params &params)
+{
+    return gaborator::parameters(params.bands_per_octave, params.ff_min, params.ff_ref, params.overlap);
+}
+
 std::unique_ptr<Analyzer> new_analyzer(const Params &params)
 {
-    gaborator::parameters params_(params.bands_per_octave, params.ff_min, params.ff_ref, params.overlap);
+    gaborator::parameters params_ = convert_params(params);
     std::unique_ptr<Analyzer> t = std::unique_ptr<Analyzer>(new Analyzer(params_));
     return t;
 }
 
+bool params_less(const Params &a, const Params &b)
+{
+    return convert_params(a) < convert_params(b);
+}
+
 
 size_t get_analysis_support_len(const Analyzer& b) { return ceil(b.analysis_support()); }
 size_t get_synthesis_support_len(const Analyzer& b) { return ceil(b.synthesis_support()); }
@@ -52,6 +137,31 @@ void process(
         coefs);
 }
 
+void process_slices(
+             Coefs &coefs,
+             int32_t from_band,
+             int32_t to_band,
+             int64_t from_sample_time,
+             int64_t to_sample_time,
+             ProcessSlicesCallback& callback)
+{
+    static_assert(sizeof(Coef) == sizeof(std::complex<float>), "Coef should be layout-compatible with std::complex<float>");
+    gaborator::apply_to_slice(
+        false,
+        [&callback](int b, int64_t st, int time_step, unsigned len, std::complex<float> *p) {
+            SliceMeta m;
+            m.band = b;
+            m.sample_time = st;
+            m.time_step = time_step;
+
+            process_slices_callback(callback, m, rust::Slice<Coef>(reinterpret_cast<Coef*>(p), len));
+        },
+        (int)from_band,
+        (int)to_band,
+        from_sample_time,
+        to_sample_time,
+        coefs);
+}
 
 void fill(
              Coefs &coefs,
@@ -105,4 +215,39 @@ void synthesize(const Analyzer& b,
         signal.data());
 }
 
-} // namespace gabbridge
\ No newline at end of file
+} // namespace gabbridge
+
+namespace gaborator {
+
+void *pod_alloc(size_t size)
+{
+    using namespace gabbridge;
+    Recycler *r = current_recycler;
+    void *block = r ? r->take(size) : nullptr;
+    if (! block) {
+        size_t capacity = size + header_size;
+        // Round up, so that the block is more likely to be reusable for other sizes
+        if (r)
+            capacity = (size_t)1 << ceil_log2(capacity);
+        block = rust_alloc(capacity);
+        if (! block)
+            throw std::bad_alloc();
+        ((BlockHeader *)block)->capacity = capacity;
+    }
+    return (char *)block + header_size;
+}
+
+void pod_free(void *p)
+{
+    using namespace gabbridge;
+    if (! p)
+        return;
+    void *block = (char *)p - header_size;
+    size_t capacity = ((BlockHeader *)block)->capacity;
+    if (current_recycler)
+        current_recycler->give(block, capacity);
+    else
+        rust_dealloc((uint8_t *)block, capacity);
+}
+
+} // namespace gaborator
@@ -8,13 +8,36 @@ namespace gabbridge {
 struct Params;
 struct Coef;
 struct CoefMeta;
+struct SliceMeta;
 struct ProcessOrFillCallback;
+struct ProcessSlicesCallback;
 enum class WriteCoefficientsMode: uint8_t;
 
 typedef gaborator::analyzer<float> Analyzer;
 typedef gaborator::coefs<float> Coefs;
 
+// Pool of memory blocks freed by Gaborator while the recycler is active
+// on the current thread, reused for subsequent allocations.
+struct Recycler {
+    Recycler();
+    Recycler(const Recycler &) = delete;
+    ~Recycler();
+    void *take(size_t size);
+    void give(void *block, size_t capacity);
+
+    static const int n_classes = 64;
+    // Singly-linked lists of blocks with capacity of at least 2^i bytes
+    void *heads[n_classes];
+    // Recycler that was active on the thread before this one was activated
+    Recycler *previous;
+};
+
+std::unique_ptr<Recycler> new_recycler();
+void activate_recycler(Recycler &r);
+void deactivate_recycler(Recycler &r);
+
 std::unique_ptr<Analyzer> new_analyzer(const Params &params);
+bool params_less(const Params &a, const Params &b);
 size_t get_analysis_support_len(const Analyzer& b);
 size_t get_synthesis_support_len(const Analyzer& b);
 
@@ -29,6 +52,14 @@ void process(
              int64_t to_sample_time,
              ProcessOrFillCallback& callback);
 
+void process_slices(
+             Coefs &coefs,
+             int32_t from_band,
+             int32_t to_band,
+             int64_t from_sample_time,
+             int64_t to_sample_time,
+             ProcessSlicesCallback& callback);
+
 void fill(
              Coefs &coefs,
              int32_t from_band,
@@ -55,4 +86,6 @@ inline int32_t band_ref(const Analyzer& b) { return b.band_ref(); }
 
 inline double band_ff(const Analyzer& b, int32_t gbno) { return b.band_ff((int)gbno); }
 
+inline uint32_t band_step_log2(const Analyzer& b, int32_t gbno) { return b.band_step_log2((int)gbno); }
+
 } // namespace gabbridge
@@ -4,14 +4,13 @@
 //!
 //! There are two examples, but they do the same thing as the ones included in `gaborator` crate.
 
-#[deny(missing_docs)]
-
 pub extern crate cxx;
 
 #[cxx::bridge(namespace = "gabbridge")]
 mod ffi {
     #[deny(missing_docs)] // pub-reexported by the high-level crate 
     /// Corresponds to `gaborator::parameters`.
+    #[derive(Copy,Clone,Debug,PartialEq)]
     pub struct Params {
         /// The number of frequency bands per octave.
         /// Values from 6 to 384 (inclusive) are supported.
@@ -61,10 +60,31 @@ mod ffi {
         sample_time: i64,
     }
 
+    #[deny(missing_docs)]
+    /// Describes a run of consecutive coefficients of one band
+    #[derive(Copy,Clone,Debug,PartialEq,Eq,Hash)]
+    pub struct SliceMeta {
+        /// The band number of the frequency band the coefficients pertain to.
+        /// This may be either a bandpass band or the lowpass band.
+        band: i32,
+
+        /// The point in time the first coefficient of the run pertains to, in samples
+        sample_time: i64,
+
+        /// Distance between points in time of adjacent coefficients, in samples
+        time_step: i32,
+    }
+
     extern "Rust" {
         type ProcessOrFillCallback<'a>;
+        type ProcessSlicesCallback<'a>;
 
         fn process_or_write_callback(cb: &mut ProcessOrFillCallback, meta: CoefMeta, coef: &mut Coef);
+
+        fn process_slices_callback(cb: &mut ProcessSlicesCallback, meta: SliceMeta, coefs: &mut [Coef]);
+
+        unsafe fn rust_alloc(size: usize) -> *mut u8;
+        unsafe fn rust_dealloc(ptr: *mut u8, size: usize);
     }
 
     unsafe extern "C++" {
@@ -72,15 +92,24 @@ mod ffi {
 
         pub type Analyzer;
         pub type Coefs;
+        pub type Recycler;
+
+        // Functions that can allocate memory return `Err` if Gaborator throws `std::bad_alloc`
+        pub fn new_analyzer(params: &Params) -> Result<UniquePtr<Analyzer>>;
+
+        pub fn params_less(a: &Params, b: &Params) -> bool;
 
-        pub fn new_analyzer(params: &Params) -> UniquePtr<Analyzer>;
+        pub fn new_recycler() -> UniquePtr<Recycler>;
+        // Deactivation reactivates the recycler that was active before `r`, so they should be nested
+        pub fn activate_recycler(r: Pin<&mut Recycler>);
+        pub fn deactivate_recycler(r: Pin<&mut Recycler>);
 
         pub fn get_analysis_support_len(b: &Analyzer) -> usize;
         pub fn get_synthesis_support_len(b: &Analyzer) -> usize;
 
-        pub fn create_coefs(b: &Analyzer) -> UniquePtr<Coefs>;
+        pub fn create_coefs(b: &Analyzer) -> Result<UniquePtr<Coefs>>;
 
-        pub fn forget_before(b: &Analyzer, c: Pin<&mut Coefs>, limit: i64, clean_cut: bool);
+        pub fn forget_before(b: &Analyzer, c: Pin<&mut Coefs>, limit: i64, clean_cut: bool) -> Result<()>;
 
 
         pub fn process(
@@ -92,6 +121,15 @@ mod ffi {
             callback: &mut ProcessOrFillCallback,
         );
 
+        pub fn process_slices(
+            coefs: Pin<&mut Coefs>,
+            from_band: i32,
+            to_band: i32,
+            from_sample_time: i64,
+            to_sample_time: i64,
+            callback: &mut ProcessSlicesCallback,
+        );
+
         pub fn fill(
             coefs: Pin<&mut Coefs>,
             from_band: i32,
@@ -99,21 +137,21 @@ mod ffi {
             from_sample_time: i64,
             to_sample_time: i64,
             callback: &mut ProcessOrFillCallback,
-        );
+        ) -> Result<()>;
 
         pub fn analyze(
             b : &Analyzer,
             signal: &[f32],
             signal_begin_sample_number: i64,
             coefs: Pin<&mut Coefs>,
-        );
+        ) -> Result<()>;
             
         pub fn synthesize(
             b : &Analyzer,
             coefs: &Coefs,
             signal_begin_sample_number: i64,
             signal: &mut [f32],
-        );
+        ) -> Result<()>;
 
         pub fn  bandpass_bands_begin(b : &Analyzer) -> i32;
 
@@ -124,11 +162,19 @@ mod ffi {
         pub fn  band_ref(b : &Analyzer) -> i32;
 
         pub fn  band_ff(b : &Analyzer, band: i32) -> f64;
+
+        pub fn  band_step_log2(b : &Analyzer, band: i32) -> u32;
     }
 }
 
 
 pub use ffi::*;
+
+mod coef;
+mod params;
+
+#[cfg(feature = "serde")]
+mod serde_impls;
 /// Wrapper for your callback function for `fill` or `process`.
 ///
 /// Example:
@@ -140,8 +186,32 @@ pub use ffi::*;
 ///        }
 ///    ))); 
 /// ```
+#[allow(clippy::type_complexity)]
 pub struct ProcessOrFillCallback<'a>(pub Box<dyn FnMut(CoefMeta, &mut Coef) + 'a>);
 
 fn process_or_write_callback(cb: &mut ProcessOrFillCallback, meta: CoefMeta, coef: &mut Coef) {
     cb.0(meta, coef);
 }
+
+/// Wrapper for your callback function for `process_slices`.
+/// Unlike `ProcessOrFillCallback`, it borrows the callback, so calling `process_slices` does not allocate.
+pub struct ProcessSlicesCallback<'a>(pub &'a mut dyn FnMut(SliceMeta, &mut [Coef]));
+
+fn process_slices_callback(cb: &mut ProcessSlicesCallback, meta: SliceMeta, coefs: &mut [Coef]) {
+    cb.0(meta, coefs);
+}
+
+/// Alignment of memory blocks allocated by Gaborator.
+const ALLOC_ALIGN: usize = 16;
+
+/// Memory used by `pod_vector`s and coefficient slices inside Gaborator is allocated using Rust's global allocator.
+unsafe fn rust_alloc(size: usize) -> *mut u8 {
+    match std::alloc::Layout::from_size_align(size, ALLOC_ALIGN) {
+        Ok(layout) => std::alloc::alloc(layout),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+unsafe fn rust_dealloc(ptr: *mut u8, size: usize) {
+    std::alloc::dealloc(ptr, std::alloc::Layout::from_size_align_unchecked(size, ALLOC_ALIGN))
+}
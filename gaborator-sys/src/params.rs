@@ -0,0 +1,17 @@
+use crate::Params;
+
+impl Params {
+    /// Parameters for a signal with given sample rate, with frequencies given in Hz
+    /// and Gaborator's default overlap of 0.7.
+    ///
+    /// `fmin_hz` is the lower limit of the analysis frequency range,
+    /// `fref_hz` is the frequency one of the bands is aligned with (e.g. 440 for musical applications).
+    pub fn for_sample_rate(sample_rate: f64, fmin_hz: f64, fref_hz: f64, bands_per_octave: u32) -> Self {
+        Params {
+            bands_per_octave,
+            ff_min: fmin_hz / sample_rate,
+            ff_ref: fref_hz / sample_rate,
+            overlap: 0.7,
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! Serde support for the shared structs is derived using remote definitions mirroring them.
+//!
+//! `cxx_build` rejects `#[cfg_attr(...)]` on shared structs ("unsupported attribute"),
+//! and its own `#[derive(Serialize, Deserialize)]` would make serde a mandatory dependency,
+//! so the derives can't be placed on the bridge structs while keeping the `serde` feature optional.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Coef, CoefMeta, Params, SliceMeta};
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Params")]
+struct ParamsDef {
+    bands_per_octave: u32,
+    ff_min: f64,
+    ff_ref: f64,
+    overlap: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Coef")]
+struct CoefDef {
+    re: f32,
+    im: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "CoefMeta")]
+struct CoefMetaDef {
+    band: i32,
+    sample_time: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "SliceMeta")]
+struct SliceMetaDef {
+    band: i32,
+    sample_time: i64,
+    time_step: i32,
+}
+
+macro_rules! via_remote {
+    ($t:ty, $def:ty) => {
+        impl Serialize for $t {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                <$def>::serialize(self, serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $t {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                <$def>::deserialize(deserializer)
+            }
+        }
+    };
+}
+
+via_remote!(Params, ParamsDef);
+via_remote!(Coef, CoefDef);
+via_remote!(CoefMeta, CoefMetaDef);
+via_remote!(SliceMeta, SliceMetaDef);
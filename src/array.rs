@@ -0,0 +1,105 @@
+use std::ops::Range;
+
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2, ArrayViewMut1};
+use num_complex::Complex32;
+
+use crate::{Coef, Coefs, Gaborator, Grid, Interp};
+
+impl<T> Grid<T> {
+    /// Convert to `n_bands × n_frames` array, without copying the values.
+    pub fn into_array2(self) -> Array2<T> {
+        Array2::from_shape_vec((self.n_bands, self.n_frames), self.data)
+            .expect("grid data length should match its dimensions")
+    }
+
+    /// Create grid from `n_bands × n_frames` array.
+    pub fn from_array2(array: ArrayView2<T>, band_begin: i32, time_begin: i64, hop: u32) -> Self
+    where
+        T: Clone,
+    {
+        let (n_bands, n_frames) = array.dim();
+        Grid {
+            band_begin,
+            n_bands,
+            time_begin,
+            hop,
+            n_frames,
+            data: array.iter().cloned().collect(),
+        }
+    }
+}
+
+impl Grid<Coef> {
+    /// Coefficients as `n_bands × n_frames` array of complex numbers
+    pub fn complex_array(&self) -> Array2<Complex32> {
+        self.map(|c| Complex32::from(*c)).into_array2()
+    }
+
+    /// Magnitudes of coefficients as `n_bands × n_frames` array
+    pub fn magnitude_array(&self) -> Array2<f32> {
+        self.magnitudes().into_array2()
+    }
+
+    /// Phases of coefficients (in radians, from -π to π) as `n_bands × n_frames` array
+    pub fn phase_array(&self) -> Array2<f32> {
+        self.map(|c| c.arg()).into_array2()
+    }
+
+    /// Magnitudes of coefficients in decibels (`20 * log10(magnitude)`) as `n_bands × n_frames` array.
+    /// Values are clamped from below to `floor_db`, to avoid infinities for zero coefficients.
+    pub fn db_array(&self, floor_db: f32) -> Array2<f32> {
+        self.map(|c| (20.0 * c.norm().log10()).max(floor_db)).into_array2()
+    }
+}
+
+impl Coefs {
+    /// Resample coefficients to a uniform time grid (see `to_grid`) and return them as `n_bands × n_frames` array.
+    pub fn to_complex_array(
+        &mut self,
+        g: &Gaborator,
+        hop: u32,
+        time_range: Range<i64>,
+        band_range: Range<i32>,
+        interp: Interp,
+    ) -> Array2<Complex32> {
+        self.to_grid(g, hop, time_range, band_range, interp).complex_array()
+    }
+
+    /// Write `n_bands × n_frames` array of coefficients sampled on a uniform time grid,
+    /// first row corresponding to `band_begin` and first column to `time_begin` (see `fill_from_grid`).
+    pub fn fill_from_complex_array(
+        &mut self,
+        g: &Gaborator,
+        array: ArrayView2<Complex32>,
+        band_begin: i32,
+        time_begin: i64,
+        hop: u32,
+        interp: Interp,
+    ) {
+        let grid = Grid::from_array2(array, band_begin, time_begin, hop).map(|c| Coef::from(*c));
+        self.fill_from_grid(g, &grid, interp)
+    }
+}
+
+impl Gaborator {
+    /// Like `analyze`, but takes samples from an array view.
+    /// Non-contiguous views are copied first.
+    pub fn analyze_array(&self, signal: ArrayView1<f32>, signal_begin_sample_number: i64, coefs: &mut Coefs) {
+        match signal.as_slice() {
+            Some(s) => self.analyze(s, signal_begin_sample_number, coefs),
+            None => self.analyze(&signal.to_vec(), signal_begin_sample_number, coefs),
+        }
+    }
+
+    /// Like `synthesize`, but stores samples to an array view.
+    pub fn synthesize_array(&self, coefs: &Coefs, signal_begin_sample_number: i64, mut signal: ArrayViewMut1<f32>) {
+        match signal.as_slice_mut() {
+            Some(s) => self.synthesize(coefs, signal_begin_sample_number, s),
+            None => {
+                let mut buf = Array1::zeros(signal.len());
+                self.synthesize(coefs, signal_begin_sample_number, buf.as_slice_mut().unwrap());
+                signal.assign(&buf);
+            }
+        }
+    }
+}
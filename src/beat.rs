@@ -0,0 +1,155 @@
+//! Tempo estimation and beat tracking on top of an onset novelty curve (see `onset` module).
+//!
+//! Global tempo is the period maximizing autocorrelation of the novelty curve, weighted by a log-Gaussian prior
+//! around `BeatParams::prior_bpm` to avoid choosing a half or double tempo.
+//! Beats are then tracked by dynamic programming: each frame's score is its novelty plus the best score
+//! of a previous beat, penalized by how far their distance is from the beat period on logarithmic scale.
+//! Leading and trailing beats with novelty below half of its RMS over all beats are dropped.
+//!
+//! Needs the analyzer to know the sample rate (see `Gaborator::with_sample_rate`),
+//! functions return `InvalidInput` error otherwise.
+
+use std::io;
+use std::ops::Range;
+
+use crate::onset::{novelty, Novelty, OnsetParams};
+use crate::pitch::parabolic;
+use crate::{Coefs, Gaborator};
+
+/// Settings of tempo estimation and beat tracking
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BeatParams {
+    /// Range of tempos considered, in beats per minute
+    pub bpm_range: Range<f64>,
+    /// Most likely tempo, in beats per minute
+    pub prior_bpm: f64,
+    /// Standard deviation of the tempo prior, in octaves
+    pub prior_octaves: f64,
+    /// How strictly beats follow the estimated period; higher values allow less deviation
+    pub tightness: f64,
+}
+
+impl Default for BeatParams {
+    fn default() -> Self {
+        BeatParams {
+            bpm_range: 40.0..240.0,
+            prior_bpm: 120.0,
+            prior_octaves: 1.0,
+            tightness: 100.0,
+        }
+    }
+}
+
+/// Result of beat tracking
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Beats {
+    /// Global tempo, in beats per minute
+    pub bpm: f64,
+    /// Sample times of beats, in increasing order
+    pub sample_times: Vec<i64>,
+    /// Same as `sample_times`, in seconds
+    pub seconds: Vec<f64>,
+}
+
+/// Number of novelty frames per minute
+fn frames_per_minute(g: &Gaborator, novelty: &Novelty) -> io::Result<f64> {
+    Ok(60.0 * g.require_sample_rate()? / novelty.hop as f64)
+}
+
+/// Global tempo of `novelty`, in beats per minute. Returns `params.prior_bpm` if the curve is too short.
+pub fn estimate_tempo(g: &Gaborator, novelty: &Novelty, params: &BeatParams) -> io::Result<f64> {
+    let fpm = frames_per_minute(g, novelty)?;
+    let n = novelty.values.len();
+    let mean = novelty.values.iter().sum::<f32>() / n.max(1) as f32;
+    let values: Vec<f32> = novelty.values.iter().map(|v| v - mean).collect();
+
+    // Lags in frames, with a neighbour on each side for interpolation
+    let min_lag = ((fpm / params.bpm_range.end).floor() as usize).max(2) - 1;
+    let max_lag = ((fpm / params.bpm_range.start).ceil() as usize + 1).min(n.saturating_sub(1));
+    if min_lag + 2 > max_lag {
+        return Ok(params.prior_bpm);
+    }
+    let scores: Vec<f32> = (min_lag..=max_lag)
+        .map(|lag| {
+            let acf: f32 = values.iter().zip(&values[lag..]).map(|(a, b)| a * b).sum::<f32>() / (n - lag) as f32;
+            let octaves = (fpm / lag as f64 / params.prior_bpm).log2() / params.prior_octaves;
+            acf * (-0.5 * octaves * octaves).exp() as f32
+        })
+        .collect();
+    // Neighbours at the ends only serve interpolation
+    let best = (1..scores.len() - 1).max_by(|a, b| scores[*a].total_cmp(&scores[*b])).unwrap_or(1);
+    Ok(fpm / (min_lag as f64 + parabolic(&scores, best)))
+}
+
+/// Estimate tempo of `novelty` and track beats through it
+pub fn track_beats(g: &Gaborator, novelty: &Novelty, params: &BeatParams) -> io::Result<Beats> {
+    let bpm = estimate_tempo(g, novelty, params)?;
+    let fpm = frames_per_minute(g, novelty)?;
+    let period = fpm / bpm;
+    let values = &novelty.values;
+    let n = values.len();
+
+    let mean = values.iter().sum::<f32>() as f64 / n.max(1) as f64;
+    let variance = values.iter().map(|v| (*v as f64 - mean).powi(2)).sum::<f64>() / n.max(1) as f64;
+    let scale = if variance > 0.0 { 1.0 / variance.sqrt() } else { 1.0 };
+
+    // Best score of a beat sequence ending at each frame, and the previous beat of that sequence
+    let mut scores = vec![0.0f64; n];
+    let mut previous: Vec<Option<usize>> = vec![None; n];
+    for t in 0..n {
+        let from = (t as f64 - 2.0 * period).ceil().max(0.0) as usize;
+        let to = (t as f64 - period / 2.0).floor();
+        let mut best: Option<(usize, f64)> = None;
+        if to >= 0.0 {
+            for (p, previous_score) in scores.iter().enumerate().take(to as usize + 1).skip(from) {
+                let deviation = ((t - p) as f64 / period).ln();
+                let score = previous_score - params.tightness * deviation * deviation;
+                if best.is_none_or(|(_, s)| score > s) {
+                    best = Some((p, score));
+                }
+            }
+        }
+        scores[t] = values[t] as f64 * scale + best.map_or(0.0, |(_, s)| s);
+        previous[t] = best.map(|(p, _)| p);
+    }
+
+    // Last beat is the best one within the last period
+    let mut frames = Vec::new();
+    let last_from = n.saturating_sub(period.round() as usize);
+    let mut beat = (last_from..n).max_by(|a, b| scores[*a].total_cmp(&scores[*b]));
+    while let Some(t) = beat {
+        frames.push(t);
+        beat = previous[t];
+    }
+    frames.reverse();
+
+    // Beats extend into silence at the ends, drop those with weak novelty
+    let rms = (frames.iter().map(|f| (values[*f] as f64).powi(2)).sum::<f64>() / frames.len().max(1) as f64).sqrt();
+    let strong = |f: &usize| values[*f] as f64 >= 0.5 * rms;
+    let first = frames.iter().position(strong).unwrap_or(frames.len());
+    let last = frames.iter().rposition(strong).map_or(first, |i| i + 1);
+    frames.truncate(last);
+    frames.drain(..first);
+
+    let sample_times: Vec<i64> = frames.iter().map(|f| novelty.frame_time(*f)).collect();
+    let sample_rate = g.require_sample_rate()?;
+    Ok(Beats {
+        bpm,
+        seconds: sample_times.iter().map(|t| *t as f64 / sample_rate).collect(),
+        sample_times,
+    })
+}
+
+/// Track beats within `time_range` of `coefs`, using novelty computed with all bands weighted equally
+/// (see `onset::novelty`).
+pub fn detect_beats(
+    g: &Gaborator,
+    coefs: &mut Coefs,
+    time_range: Range<i64>,
+    onset_params: &OnsetParams,
+    params: &BeatParams,
+) -> io::Result<Beats> {
+    track_beats(g, &novelty(g, coefs, time_range, onset_params, |_| 1.0)?, params)
+}
@@ -0,0 +1,84 @@
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use crate::{Gaborator, GaboratorParams};
+
+/// Map key ordered using C++'s `gaborator::parameters::operator<`
+struct ParamsKey(GaboratorParams);
+
+impl PartialEq for ParamsKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == std::cmp::Ordering::Equal
+    }
+}
+impl Eq for ParamsKey {}
+
+impl PartialOrd for ParamsKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for ParamsKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        if gaborator_sys::params_less(&self.0, &other.0) {
+            std::cmp::Ordering::Less
+        } else if gaborator_sys::params_less(&other.0, &self.0) {
+            std::cmp::Ordering::Greater
+        } else {
+            std::cmp::Ordering::Equal
+        }
+    }
+}
+
+/// Parameters and bit pattern of the sample rate, if any
+type CacheKey = (ParamsKey, Option<u64>);
+
+static CACHE: Mutex<BTreeMap<CacheKey, Arc<Gaborator>>> = Mutex::new(BTreeMap::new());
+
+/// Process-wide cache of `Gaborator` instances, keyed by parameters (and sample rate, if given).
+///
+/// Creating an analyzer with many bands per octave is expensive (filter bank and FFT plan setup),
+/// so short-lived jobs can share instances instead of rebuilding them each time.
+/// Cached analyzers live until [`GaboratorCache::clear`] is called.
+pub struct GaboratorCache;
+
+impl GaboratorCache {
+    /// Get analyzer for the given parameters, creating it if it is not cached yet.
+    pub fn get(params: &GaboratorParams) -> Arc<Gaborator> {
+        Self::get_or_create((ParamsKey(*params), None), || Gaborator::new(params))
+    }
+
+    /// Like `get`, but for analyzer that remembers the sample rate (see `Gaborator::with_sample_rate`).
+    pub fn get_with_sample_rate(params: &GaboratorParams, sample_rate: f64) -> Arc<Gaborator> {
+        Self::get_or_create((ParamsKey(*params), Some(sample_rate.to_bits())), || {
+            Gaborator::with_sample_rate(params, sample_rate)
+        })
+    }
+
+    /// Look up `key`, or create the analyzer outside of the cache lock so that lookups of other
+    /// parameters are not blocked meanwhile. If another thread has cached one for the same key in the meantime,
+    /// that one is returned and the new one dropped.
+    fn get_or_create(key: CacheKey, create: impl FnOnce() -> Gaborator) -> Arc<Gaborator> {
+        if let Some(g) = CACHE.lock().unwrap_or_else(|e| e.into_inner()).get(&key) {
+            return g.clone();
+        }
+        let created = Arc::new(create());
+        let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        match cache.entry(key) {
+            // `created` is dropped after the cache lock is released
+            Entry::Occupied(e) => e.get().clone(),
+            Entry::Vacant(e) => e.insert(created).clone(),
+        }
+    }
+
+    /// Remove all analyzers from the cache.
+    /// Instances that are still referenced elsewhere stay alive until their last `Arc` is dropped.
+    pub fn clear() {
+        let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        // Drop outside of the cache lock
+        let old = std::mem::take(&mut *cache);
+        drop(cache);
+        drop(old);
+    }
+}
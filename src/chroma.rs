@@ -0,0 +1,121 @@
+//! Chromagram: pitch class profiles for harmony analysis, e.g. chord or key recognition.
+//!
+//! Energy of bands is folded into 12 pitch classes regardless of octave, tuned to a configurable A4 frequency.
+use std::io;
+use std::ops::Range;
+
+use crate::notes::A4_MIDI;
+use crate::{Coefs, Gaborator};
+
+/// Normalization of each chroma vector
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChromaNorm {
+    /// Keep energies as they are
+    None,
+    /// Divide by the largest value, so the strongest pitch class is 1
+    Max,
+    /// Divide by the sum of values
+    L1,
+    /// Divide by the Euclidean norm
+    L2,
+}
+
+/// Settings of `chroma`
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChromaParams {
+    /// Frequency of A4 in Hz, to which pitch classes are tuned
+    pub tuning_hz: f64,
+    /// Only bands with center frequencies within this range (in Hz) are used
+    pub hz_range: Range<f64>,
+    /// Number of frames of centered moving average applied before normalization; 0 or 1 means no smoothing
+    pub smoothing_frames: usize,
+    /// Normalization of each frame, applied last
+    pub norm: ChromaNorm,
+}
+
+impl Default for ChromaParams {
+    fn default() -> Self {
+        ChromaParams {
+            tuning_hz: 440.0,
+            hz_range: 55.0..5000.0,
+            smoothing_frames: 1,
+            norm: ChromaNorm::Max,
+        }
+    }
+}
+
+/// Pitch class profiles: energy of each of 12 pitch classes (index 0 is C, 9 is A) for frames
+/// every `hop` samples, starting from `time_range.start` and not reaching `time_range.end`.
+///
+/// Energy of each band within a frame (see `Coefs::to_energy_grid`)
+/// is split between the two pitch classes nearest to its band's center frequency,
+/// proportionally to the distance on logarithmic scale. With note-aligned bands each band goes to a single pitch class.
+///
+/// Needs the analyzer to know the sample rate (see `Gaborator::with_sample_rate`), returns `InvalidInput` error otherwise.
+pub fn chroma(
+    g: &Gaborator,
+    coefs: &mut Coefs,
+    hop: u32,
+    time_range: Range<i64>,
+    params: &ChromaParams,
+) -> io::Result<Vec<[f32; 12]>> {
+    let sample_rate = g.require_sample_rate()?;
+    let bands = g.bands_for_ff_range(params.hz_range.start / sample_rate..params.hz_range.end / sample_rate);
+    let bands = bands.start..bands.end.min(g.bandpass_bands_end());
+    let energy = coefs.to_energy_grid(g, hop, time_range, bands);
+
+    let mut frames = vec![[0.0f32; 12]; energy.n_frames];
+    for band in energy.band_begin..energy.band_end() {
+        // Split between the two nearest pitch classes
+        let midi = A4_MIDI as f64 + 12.0 * (g.band_ff(band) * sample_rate / params.tuning_hz).log2();
+        let lower = midi.floor();
+        let frac = (midi - lower) as f32;
+        let pc = (lower as i64).rem_euclid(12) as usize;
+        for (frame, e) in frames.iter_mut().zip(energy.row(band)) {
+            frame[pc] += e * (1.0 - frac);
+            frame[(pc + 1) % 12] += e * frac;
+        }
+    }
+
+    if params.smoothing_frames > 1 {
+        frames = smooth(&frames, params.smoothing_frames);
+    }
+    for frame in &mut frames {
+        normalize(frame, params.norm);
+    }
+    Ok(frames)
+}
+
+/// Centered moving average over `width` frames, shrinking at the edges
+fn smooth(frames: &[[f32; 12]], width: usize) -> Vec<[f32; 12]> {
+    let before = (width - 1) / 2;
+    let after = width - 1 - before;
+    (0..frames.len())
+        .map(|i| {
+            let window = &frames[i.saturating_sub(before)..(i + after + 1).min(frames.len())];
+            let mut sum = [0.0f32; 12];
+            for frame in window {
+                for (s, x) in sum.iter_mut().zip(frame) {
+                    *s += *x;
+                }
+            }
+            sum.map(|s| s / window.len() as f32)
+        })
+        .collect()
+}
+
+fn normalize(frame: &mut [f32; 12], norm: ChromaNorm) {
+    let divisor = match norm {
+        ChromaNorm::None => return,
+        ChromaNorm::Max => frame.iter().cloned().fold(0.0, f32::max),
+        ChromaNorm::L1 => frame.iter().sum(),
+        ChromaNorm::L2 => frame.iter().map(|x| x * x).sum::<f32>().sqrt(),
+    };
+    if divisor > 0.0 {
+        for x in frame.iter_mut() {
+            *x /= divisor;
+        }
+    }
+}
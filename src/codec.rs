@@ -0,0 +1,450 @@
+//! Lossy compression of coefficients, used by `Coefs::save_lossy` and `Coefs::load_lossy`.
+//!
+//! Magnitudes are quantized on logarithmic scale relative to the loudest coefficient of each band,
+//! phases are predicted from the previous coefficient of the band, and both are range coded.
+
+use std::io::{self, Read, Write};
+
+use crate::file_format::{
+    check_params, invalid_data, read_array, read_f32, read_i32, read_i64, read_params, read_u32, read_u64, write_params,
+    RunChecker,
+};
+use crate::{Coef, Coefs, Gaborator, SliceMeta};
+
+const MAGIC: &[u8; 8] = b"GABLOSSY";
+const VERSION: u32 = 1;
+
+/// Upper bound for number of coefficients per byte of range coded payload, to reject corrupt files
+/// before decoding. Each coefficient takes at least one binary decision, and a decision costs at least
+/// `log2(2048 / 2017)` ≈ 0.022 bits once its probability has adapted, so a byte holds at most about 364 of them.
+const MAX_COEFS_PER_BYTE: u64 = 400;
+
+/// Settings of the lossy coefficient codec used by `Coefs::save_lossy`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LossyParams {
+    /// Coefficients quieter than the loudest coefficient of their band by more than this many decibels are dropped (read as zero).
+    pub threshold_db: f32,
+    /// Number of bits for magnitude, quantized on logarithmic scale between `-threshold_db` and 0 dB relative to the band's loudest coefficient.
+    /// Values from 1 to 16 are supported.
+    pub magnitude_bits: u8,
+    /// Number of bits for phase. Values from 1 to 16 are supported.
+    pub phase_bits: u8,
+}
+
+impl Default for LossyParams {
+    fn default() -> Self {
+        LossyParams {
+            threshold_db: 60.0,
+            magnitude_bits: 8,
+            phase_bits: 6,
+        }
+    }
+}
+
+/// Signal-to-noise ratio of `signal` compared to `reference`, in decibels.
+/// Extra samples of the longer slice are ignored.
+pub fn snr_db(reference: &[f32], signal: &[f32]) -> f64 {
+    let mut power = 0.0;
+    let mut noise = 0.0;
+    for (r, s) in reference.iter().zip(signal) {
+        power += (*r as f64) * (*r as f64);
+        noise += (*r as f64 - *s as f64) * (*r as f64 - *s as f64);
+    }
+    10.0 * (power / noise).log10()
+}
+
+// Adaptive binary range coder, in the style of LZMA's.
+
+const PROB_BITS: u32 = 11;
+const PROB_INIT: u16 = 1 << (PROB_BITS - 1);
+const MOVE_BITS: u32 = 5;
+const TOP: u32 = 1 << 24;
+
+struct RangeEncoder {
+    low: u64,
+    range: u32,
+    cache: u8,
+    cache_size: u64,
+    out: Vec<u8>,
+}
+
+impl RangeEncoder {
+    fn new() -> Self {
+        RangeEncoder {
+            low: 0,
+            range: u32::MAX,
+            cache: 0,
+            cache_size: 1,
+            out: Vec::new(),
+        }
+    }
+
+    fn encode_bit(&mut self, prob: &mut u16, bit: bool) {
+        let bound = (self.range >> PROB_BITS) * *prob as u32;
+        if !bit {
+            self.range = bound;
+            *prob += ((1 << PROB_BITS) - *prob) >> MOVE_BITS;
+        } else {
+            self.low += bound as u64;
+            self.range -= bound;
+            *prob -= *prob >> MOVE_BITS;
+        }
+        while self.range < TOP {
+            self.range <<= 8;
+            self.shift_low();
+        }
+    }
+
+    fn shift_low(&mut self) {
+        if self.low < 0xFF00_0000 || self.low > u32::MAX as u64 {
+            let carry = (self.low >> 32) as u8;
+            let mut temp = self.cache;
+            loop {
+                self.out.push(temp.wrapping_add(carry));
+                temp = 0xFF;
+                self.cache_size -= 1;
+                if self.cache_size == 0 {
+                    break;
+                }
+            }
+            self.cache = (self.low >> 24) as u8;
+        }
+        self.cache_size += 1;
+        self.low = (self.low & 0x00FF_FFFF) << 8;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        for _ in 0..5 {
+            self.shift_low();
+        }
+        self.out
+    }
+}
+
+struct RangeDecoder<'a> {
+    range: u32,
+    code: u32,
+    input: &'a [u8],
+}
+
+impl<'a> RangeDecoder<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        let mut d = RangeDecoder {
+            range: u32::MAX,
+            code: 0,
+            input,
+        };
+        for _ in 0..5 {
+            d.code = (d.code << 8) | d.next_byte() as u32;
+        }
+        d
+    }
+
+    /// Bytes past the end of input read as zero; truncated input decodes to garbage, but does not crash.
+    fn next_byte(&mut self) -> u8 {
+        match self.input.split_first() {
+            Some((b, rest)) => {
+                self.input = rest;
+                *b
+            }
+            None => 0,
+        }
+    }
+
+    fn decode_bit(&mut self, prob: &mut u16) -> bool {
+        let bound = (self.range >> PROB_BITS) * *prob as u32;
+        let bit = if self.code < bound {
+            self.range = bound;
+            *prob += ((1 << PROB_BITS) - *prob) >> MOVE_BITS;
+            false
+        } else {
+            self.code -= bound;
+            self.range -= bound;
+            *prob -= *prob >> MOVE_BITS;
+            true
+        };
+        while self.range < TOP {
+            self.range <<= 8;
+            self.code = (self.code << 8) | self.next_byte() as u32;
+        }
+        bit
+    }
+}
+
+/// Adaptive model for symbols of fixed number of bits, coded most significant bit first.
+struct BitTree {
+    bits: u8,
+    probs: Vec<u16>,
+}
+
+impl BitTree {
+    fn new(bits: u8) -> Self {
+        BitTree {
+            bits,
+            probs: vec![PROB_INIT; 1 << bits],
+        }
+    }
+
+    fn encode(&mut self, e: &mut RangeEncoder, symbol: u32) {
+        let mut m = 1;
+        for i in (0..self.bits).rev() {
+            let bit = (symbol >> i) & 1;
+            e.encode_bit(&mut self.probs[m], bit != 0);
+            m = (m << 1) | bit as usize;
+        }
+    }
+
+    fn decode(&mut self, d: &mut RangeDecoder) -> u32 {
+        let mut m = 1;
+        for _ in 0..self.bits {
+            let bit = d.decode_bit(&mut self.probs[m]);
+            m = (m << 1) | bit as usize;
+        }
+        (m - (1 << self.bits)) as u32
+    }
+}
+
+/// Probability models shared by encoder and decoder, so both stay in lockstep.
+struct Model {
+    params: LossyParams,
+    /// Whether a coefficient is kept, given whether the previous one in the band was kept
+    kept: [u16; 2],
+    magnitude: BitTree,
+    /// Phase difference from the previous coefficient of the band
+    phase_predicted: BitTree,
+    /// Phase when there is no previous coefficient to predict from
+    phase_raw: BitTree,
+}
+
+/// Per-band state of coding
+struct BandState {
+    band_max: f32,
+    /// Quantized phase of the previous coefficient, if it was kept
+    prev_phase: Option<u32>,
+}
+
+impl Model {
+    fn new(params: LossyParams) -> Self {
+        Model {
+            params,
+            kept: [PROB_INIT; 2],
+            magnitude: BitTree::new(params.magnitude_bits),
+            phase_predicted: BitTree::new(params.phase_bits),
+            phase_raw: BitTree::new(params.phase_bits),
+        }
+    }
+
+    fn magnitude_levels(&self) -> u32 {
+        1 << self.params.magnitude_bits
+    }
+
+    fn phase_levels(&self) -> u32 {
+        1 << self.params.phase_bits
+    }
+
+    fn band_state(band_max: f32) -> BandState {
+        BandState {
+            band_max,
+            prev_phase: None,
+        }
+    }
+
+    /// Quantized magnitude and phase, or `None` if the coefficient should be dropped
+    fn quantize(&self, c: Coef, band_max: f32) -> Option<(u32, u32)> {
+        let threshold = self.params.threshold_db;
+        let db = 20.0 * (c.norm() / band_max).log10();
+        if band_max <= 0.0 || db.is_nan() || db < -threshold {
+            return None;
+        }
+        let max_level = self.magnitude_levels() - 1;
+        let m = ((db + threshold) / threshold * max_level as f32).round().max(0.0) as u32;
+        let turns = c.arg() / std::f32::consts::TAU;
+        let p = (turns * self.phase_levels() as f32).round() as i64;
+        Some((m.min(max_level), p.rem_euclid(self.phase_levels() as i64) as u32))
+    }
+
+    fn dequantize(&self, m: u32, p: u32, band_max: f32) -> Coef {
+        let threshold = self.params.threshold_db;
+        let max_level = (self.magnitude_levels() - 1).max(1);
+        let db = m as f32 / max_level as f32 * threshold - threshold;
+        let phase = p as f32 / self.phase_levels() as f32 * std::f32::consts::TAU;
+        Coef::from_polar(band_max * 10f32.powf(db / 20.0), phase)
+    }
+
+    fn encode(&mut self, e: &mut RangeEncoder, s: &mut BandState, c: Coef) {
+        let q = self.quantize(c, s.band_max);
+        let ctx = s.prev_phase.is_some() as usize;
+        e.encode_bit(&mut self.kept[ctx], q.is_some());
+        match q {
+            Some((m, p)) => {
+                self.magnitude.encode(e, m);
+                match s.prev_phase {
+                    Some(prev) => {
+                        let diff = (p + self.phase_levels() - prev) % self.phase_levels();
+                        self.phase_predicted.encode(e, diff);
+                    }
+                    None => self.phase_raw.encode(e, p),
+                }
+                s.prev_phase = Some(p);
+            }
+            None => s.prev_phase = None,
+        }
+    }
+
+    fn decode(&mut self, d: &mut RangeDecoder, s: &mut BandState) -> Coef {
+        let ctx = s.prev_phase.is_some() as usize;
+        if !d.decode_bit(&mut self.kept[ctx]) {
+            s.prev_phase = None;
+            return Coef::default();
+        }
+        let m = self.magnitude.decode(d);
+        let p = match s.prev_phase {
+            Some(prev) => (prev + self.phase_predicted.decode(d)) % self.phase_levels(),
+            None => self.phase_raw.decode(d),
+        };
+        s.prev_phase = Some(p);
+        self.dequantize(m, p, s.band_max)
+    }
+}
+
+impl Coefs {
+    /// Save all existing coefficients in a compressed lossy format.
+    ///
+    /// Coefficients below `settings.threshold_db` relative to the loudest coefficient of their band are dropped,
+    /// the rest are quantized on logarithmic magnitude scale and phase, then entropy-coded.
+    /// Phase is predicted from the previous coefficient of the band, since coefficients are demodulated
+    /// and phase of a stationary sound changes slowly.
+    /// Use `snr_db` on the output of `synthesize` to measure the quality loss.
+    pub fn save_lossy(&mut self, g: &Gaborator, mut w: impl Write, settings: &LossyParams) -> io::Result<()> {
+        assert!((1..=16).contains(&settings.magnitude_bits), "magnitude_bits should be from 1 to 16");
+        assert!((1..=16).contains(&settings.phase_bits), "phase_bits should be from 1 to 16");
+        assert!(settings.threshold_db > 0.0, "threshold_db should be positive");
+        check_params(self.params(), g)?;
+
+        let mut runs: Vec<(SliceMeta, Vec<Coef>)> = Vec::new();
+        let n_bands = (g.band_lowpass() + 1) as usize;
+        let mut band_max = vec![0.0f32; n_bands];
+        self.process_slices(i32::MIN, i32::MAX, i64::MIN, i64::MAX, |meta, coefs| {
+            for c in coefs.iter() {
+                let m = &mut band_max[meta.band as usize];
+                *m = m.max(c.norm());
+            }
+            runs.push((meta, coefs.to_vec()));
+        });
+
+        let mut model = Model::new(*settings);
+        let mut e = RangeEncoder::new();
+        let mut state: Option<(i32, BandState)> = None;
+        for (meta, coefs) in &runs {
+            if state.as_ref().map(|s| s.0) != Some(meta.band) {
+                state = Some((meta.band, Model::band_state(band_max[meta.band as usize])));
+            }
+            let s = &mut state.as_mut().unwrap().1;
+            for c in coefs {
+                model.encode(&mut e, s, *c);
+            }
+        }
+        let payload = e.finish();
+
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        write_params(&mut w, self.params())?;
+        w.write_all(&settings.threshold_db.to_le_bytes())?;
+        w.write_all(&[settings.magnitude_bits, settings.phase_bits])?;
+        w.write_all(&(n_bands as u32).to_le_bytes())?;
+        for m in &band_max {
+            w.write_all(&m.to_le_bytes())?;
+        }
+        w.write_all(&(runs.len() as u64).to_le_bytes())?;
+        for (meta, coefs) in &runs {
+            w.write_all(&meta.band.to_le_bytes())?;
+            w.write_all(&meta.sample_time.to_le_bytes())?;
+            w.write_all(&(coefs.len() as u32).to_le_bytes())?;
+        }
+        w.write_all(&(payload.len() as u64).to_le_bytes())?;
+        w.write_all(&payload)?;
+        w.flush()
+    }
+
+    /// Load coefficients saved by `save_lossy`.
+    ///
+    /// Fails with `InvalidData` error if the data is not in the expected format,
+    /// was saved with parameters different from `g`'s, or its coefficients span more than 2^32 samples
+    /// (see `Coefs::load`).
+    pub fn load_lossy(mut r: impl Read, g: &Gaborator) -> io::Result<Coefs> {
+        if &read_array::<8>(&mut r)? != MAGIC {
+            return Err(invalid_data("not a lossy Gaborator coefficients file"));
+        }
+        let version = read_u32(&mut r)?;
+        if version != VERSION {
+            return Err(invalid_data(format!("unsupported file format version {}", version)));
+        }
+        check_params(&read_params(&mut r)?, g)?;
+        let threshold_db = read_f32(&mut r)?;
+        let [magnitude_bits, phase_bits] = read_array(&mut r)?;
+        if !(1..=16).contains(&magnitude_bits) || !(1..=16).contains(&phase_bits) || threshold_db.is_nan() || threshold_db <= 0.0 {
+            return Err(invalid_data("invalid codec settings"));
+        }
+        let settings = LossyParams {
+            threshold_db,
+            magnitude_bits,
+            phase_bits,
+        };
+
+        let n_bands = read_u32(&mut r)? as usize;
+        if n_bands != (g.band_lowpass() + 1) as usize {
+            return Err(invalid_data("number of bands does not match the analyzer"));
+        }
+        let mut band_max = Vec::with_capacity(n_bands);
+        for _ in 0..n_bands {
+            band_max.push(read_f32(&mut r)?);
+        }
+
+        let n_runs = read_u64(&mut r)?;
+        let mut runs = Vec::new();
+        let mut total_len = 0u64;
+        let mut checker = RunChecker::default();
+        for _ in 0..n_runs {
+            let band = read_i32(&mut r)?;
+            let sample_time = read_i64(&mut r)?;
+            let len = read_u32(&mut r)?;
+            checker.check(g, band, sample_time, len)?;
+            let time_step = g.band_time_step(band);
+            runs.push((SliceMeta { band, sample_time, time_step: time_step as i32 }, len));
+            total_len = total_len.saturating_add(len as u64);
+        }
+
+        let payload_len = read_u64(&mut r)?;
+        if total_len > payload_len.saturating_add(1).saturating_mul(MAX_COEFS_PER_BYTE) {
+            return Err(invalid_data("more coefficients than the payload can hold"));
+        }
+        let mut payload = Vec::new();
+        r.take(payload_len).read_to_end(&mut payload)?;
+        if payload.len() as u64 != payload_len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let mut coefs = Coefs::new(g);
+        let mut model = Model::new(settings);
+        let mut d = RangeDecoder::new(&payload);
+        let mut state: Option<(i32, BandState)> = None;
+        let mut values = Vec::new();
+        for (meta, len) in runs {
+            if state.as_ref().map(|s| s.0) != Some(meta.band) {
+                state = Some((meta.band, Model::band_state(band_max[meta.band as usize])));
+            }
+            let s = &mut state.as_mut().unwrap().1;
+            values.clear();
+            for _ in 0..len {
+                values.push(model.decode(&mut d, s));
+            }
+            let step = meta.time_step as i64;
+            let end_time = meta.sample_time + len as i64 * step;
+            coefs.fill(meta.band, meta.band + 1, meta.sample_time, end_time, |m, coef| {
+                *coef = values[((m.sample_time - meta.sample_time) / step) as usize];
+            });
+        }
+        Ok(coefs)
+    }
+}
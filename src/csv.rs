@@ -0,0 +1,317 @@
+//! Text representation of coefficients as CSV, one coefficient per row.
+//!
+//! The file starts with `#`-prefixed `key=value` lines recording analyzer parameters and sample rate,
+//! followed by a column header line, either `sample_time,band,magnitude,phase` or `sample_time,band,re,im`:
+//!
+//! ```text
+//! # gaborator coefficients
+//! # bands_per_octave=48
+//! # ff_min=0.001
+//! # ff_ref=0.009166666666666667
+//! # overlap=0.7
+//! # sample_rate=48000
+//! sample_time,band,magnitude,phase
+//! 0,5,0.0012,-1.3
+//! ```
+//!
+//! Both reading and writing is streaming, rows are not collected in memory.
+
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+
+use crate::file_format::check_params;
+use crate::{Coef, CoefMeta, Coefs, Gaborator, GaboratorParams};
+
+/// Representation of coefficient values in CSV columns
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Columns {
+    /// `magnitude,phase` (phase in radians)
+    Polar,
+    /// `re,im`
+    Cartesian,
+}
+
+impl Columns {
+    fn header_line(self) -> &'static str {
+        match self {
+            Columns::Polar => "sample_time,band,magnitude,phase",
+            Columns::Cartesian => "sample_time,band,re,im",
+        }
+    }
+}
+
+/// Metadata stored at the beginning of CSV file
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Header {
+    /// Parameters of the analyzer the coefficients were produced with
+    pub params: GaboratorParams,
+    /// Sample rate of the analyzed signal, in Hz
+    pub sample_rate: u32,
+    /// How coefficient values are represented
+    pub columns: Columns,
+}
+
+/// Streaming writer of coefficients in CSV format.
+/// Writes many small chunks, so the underlying writer should be buffered.
+pub struct Writer<W: Write> {
+    w: W,
+    columns: Columns,
+}
+
+impl<W: Write> Writer<W> {
+    /// Write the header and return writer ready to accept rows.
+    pub fn new(mut w: W, header: &Header) -> io::Result<Self> {
+        let p = &header.params;
+        writeln!(w, "# gaborator coefficients")?;
+        writeln!(w, "# bands_per_octave={}", p.bands_per_octave)?;
+        writeln!(w, "# ff_min={}", p.ff_min)?;
+        writeln!(w, "# ff_ref={}", p.ff_ref)?;
+        writeln!(w, "# overlap={}", p.overlap)?;
+        writeln!(w, "# sample_rate={}", header.sample_rate)?;
+        writeln!(w, "{}", header.columns.header_line())?;
+        Ok(Writer {
+            w,
+            columns: header.columns,
+        })
+    }
+
+    /// Write one coefficient
+    pub fn write_row(&mut self, meta: CoefMeta, coef: Coef) -> io::Result<()> {
+        let (a, b) = match self.columns {
+            Columns::Polar => coef.to_polar(),
+            Columns::Cartesian => (coef.re, coef.im),
+        };
+        writeln!(self.w, "{},{},{},{}", meta.sample_time, meta.band, a, b)
+    }
+
+    /// Write all existing coefficients within the given range (same as in `Coefs::process`),
+    /// band by band in order of time.
+    pub fn write_coefs(
+        &mut self,
+        coefs: &mut Coefs,
+        from_band: i32,
+        to_band: i32,
+        from_sample_time: i64,
+        to_sample_time: i64,
+    ) -> io::Result<()> {
+        let mut result: io::Result<()> = Ok(());
+        coefs.process_slices(from_band, to_band, from_sample_time, to_sample_time, |meta, cs| {
+            for (i, c) in cs.iter().enumerate() {
+                if result.is_err() {
+                    return;
+                }
+                let sample_time = meta.sample_time + i as i64 * meta.time_step as i64;
+                result = self.write_row(CoefMeta { band: meta.band, sample_time }, *c);
+            }
+        });
+        result
+    }
+
+    /// Flush and return the underlying writer
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.w.flush()?;
+        Ok(self.w)
+    }
+}
+
+/// Streaming reader of coefficients in CSV format.
+///
+/// Iterating over it yields rows one by one.
+/// Errors are `InvalidData` with a message mentioning the line number.
+pub struct Reader<R: BufRead> {
+    r: R,
+    header: Header,
+    line_number: u64,
+    line: String,
+}
+
+fn parse_error(line_number: u64, msg: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line_number, msg))
+}
+
+fn parse_field<T: std::str::FromStr>(line_number: u64, name: &str, value: Option<&str>) -> io::Result<T> {
+    let value = value.ok_or_else(|| parse_error(line_number, format!("missing {} column", name)))?;
+    value
+        .trim()
+        .parse()
+        .map_err(|_| parse_error(line_number, format!("invalid {} '{}'", name, value)))
+}
+
+impl<R: BufRead> Reader<R> {
+    /// Read the header. Fails if it is missing or incomplete.
+    pub fn new(r: R) -> io::Result<Self> {
+        let mut reader = Reader {
+            r,
+            header: Header {
+                params: GaboratorParams {
+                    bands_per_octave: 0,
+                    ff_min: 0.0,
+                    ff_ref: 0.0,
+                    overlap: 0.0,
+                },
+                sample_rate: 0,
+                columns: Columns::Polar,
+            },
+            line_number: 0,
+            line: String::new(),
+        };
+
+        let mut bands_per_octave = None;
+        let mut ff_min = None;
+        let mut ff_ref = None;
+        let mut overlap = None;
+        let mut sample_rate = None;
+        let columns = loop {
+            if !reader.next_line()? {
+                return Err(parse_error(reader.line_number + 1, "missing column header"));
+            }
+            let n = reader.line_number;
+            let line = reader.line.trim();
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some((key, value)) = comment.split_once('=') {
+                    let value = Some(value);
+                    match key.trim() {
+                        "bands_per_octave" => bands_per_octave = Some(parse_field(n, key, value)?),
+                        "ff_min" => ff_min = Some(parse_field(n, key, value)?),
+                        "ff_ref" => ff_ref = Some(parse_field(n, key, value)?),
+                        "overlap" => overlap = Some(parse_field(n, key, value)?),
+                        "sample_rate" => sample_rate = Some(parse_field(n, key, value)?),
+                        _ => (),
+                    }
+                }
+                continue;
+            }
+            let header_line: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+            break match header_line.as_str() {
+                l if l == Columns::Polar.header_line() => Columns::Polar,
+                l if l == Columns::Cartesian.header_line() => Columns::Cartesian,
+                _ => return Err(parse_error(n, format!("unexpected column header '{}'", line))),
+            };
+        };
+
+        let missing = |key| parse_error(reader.line_number, format!("header is missing {}", key));
+        reader.header = Header {
+            params: GaboratorParams {
+                bands_per_octave: bands_per_octave.ok_or_else(|| missing("bands_per_octave"))?,
+                ff_min: ff_min.ok_or_else(|| missing("ff_min"))?,
+                ff_ref: ff_ref.ok_or_else(|| missing("ff_ref"))?,
+                overlap: overlap.ok_or_else(|| missing("overlap"))?,
+            },
+            sample_rate: sample_rate.ok_or_else(|| missing("sample_rate"))?,
+            columns,
+        };
+        Ok(reader)
+    }
+
+    /// Metadata read from the beginning of the file
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Number of the last line read, starting from 1
+    pub fn line_number(&self) -> u64 {
+        self.line_number
+    }
+
+    /// Read next line into `self.line`. Returns false at the end of input.
+    fn next_line(&mut self) -> io::Result<bool> {
+        self.line.clear();
+        if self.r.read_line(&mut self.line)? == 0 {
+            return Ok(false);
+        }
+        self.line_number += 1;
+        Ok(true)
+    }
+
+    /// Read next coefficient, skipping empty lines. Returns `None` at the end of input.
+    pub fn read_row(&mut self) -> io::Result<Option<(CoefMeta, Coef)>> {
+        loop {
+            if !self.next_line()? {
+                return Ok(None);
+            }
+            if !self.line.trim().is_empty() {
+                break;
+            }
+        }
+        let n = self.line_number;
+        let mut fields = self.line.trim().split(',');
+        let sample_time = parse_field(n, "sample_time", fields.next())?;
+        let band = parse_field(n, "band", fields.next())?;
+        let coef = match self.header.columns {
+            Columns::Polar => Coef::from_polar(
+                parse_field(n, "magnitude", fields.next())?,
+                parse_field(n, "phase", fields.next())?,
+            ),
+            Columns::Cartesian => Coef::new(parse_field(n, "re", fields.next())?, parse_field(n, "im", fields.next())?),
+        };
+        if fields.next().is_some() {
+            return Err(parse_error(n, "too many columns"));
+        }
+        Ok(Some((CoefMeta { band, sample_time }, coef)))
+    }
+
+    /// Read all remaining rows into `coefs`, which should have been created for `g`.
+    /// Rows not mentioned in the file are left intact.
+    ///
+    /// Fails if the header's parameters differ from `g`'s, or if a row's band or time does not exist in `g`.
+    /// Returns the range of sample times of the rows read (empty if there were none).
+    pub fn read_into(&mut self, coefs: &mut Coefs, g: &Gaborator) -> io::Result<Range<i64>> {
+        check_params(&self.header.params, g)?;
+        let mut time_range: Option<Range<i64>> = None;
+
+        // Consecutive coefficients of the same band are collected into a run and written at once.
+        let mut run_band = 0;
+        let mut run_start = 0;
+        let mut run_step = 1;
+        let mut run = Vec::new();
+        let flush = |coefs: &mut Coefs, band: i32, start: i64, step: i64, run: &mut Vec<Coef>| {
+            if run.is_empty() {
+                return;
+            }
+            let end = start + run.len() as i64 * step;
+            coefs.fill(band, band + 1, start, end, |meta, coef| {
+                *coef = run[((meta.sample_time - start) / step) as usize];
+            });
+            run.clear();
+        };
+
+        while let Some((meta, coef)) = self.read_row()? {
+            let n = self.line_number;
+            if meta.band < g.bandpass_bands_begin() || meta.band > g.band_lowpass() {
+                return Err(parse_error(n, format!("band {} does not exist", meta.band)));
+            }
+            let step = g.band_time_step(meta.band);
+            if meta.sample_time % step != 0 {
+                return Err(parse_error(
+                    n,
+                    format!("sample time {} is not a multiple of band's time step {}", meta.sample_time, step),
+                ));
+            }
+
+            if run.is_empty() || meta.band != run_band || meta.sample_time != run_start + run.len() as i64 * run_step {
+                flush(coefs, run_band, run_start, run_step, &mut run);
+                run_band = meta.band;
+                run_start = meta.sample_time;
+                run_step = step;
+            }
+            run.push(coef);
+
+            time_range = Some(match time_range {
+                Some(r) => r.start.min(meta.sample_time)..r.end.max(meta.sample_time + 1),
+                None => meta.sample_time..meta.sample_time + 1,
+            });
+        }
+        flush(coefs, run_band, run_start, run_step, &mut run);
+        Ok(time_range.unwrap_or(0..0))
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = io::Result<(CoefMeta, Coef)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_row().transpose()
+    }
+}
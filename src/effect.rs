@@ -0,0 +1,122 @@
+use std::io;
+
+use crate::{Coef, Gaborator, SliceMeta};
+
+/// Reusable spectral processing component.
+///
+/// Can be applied offline to whole `Coefs` or `MultiCoefs` using `Coefs::apply_effect` / `MultiCoefs::apply_effect`
+/// or to a stream using `StreamingProcessor::process_block_with_effect` / `StreamingProcessor::process_interleaved_with_effect`.
+pub trait SpectralEffect {
+    /// Get ready to process coefficients produced by `g`, e.g. precalculate per-band tables.
+    /// Should be called before the first `process`.
+    fn prepare(&mut self, g: &Gaborator) {
+        let _ = g;
+    }
+
+    /// Read or modify a run of consecutive coefficients of one band.
+    ///
+    /// For each band, runs arrive in chronological order, but runs of different bands may be interleaved arbitrarily.
+    fn process(&mut self, meta: SliceMeta, coefs: &mut [Coef]);
+
+    /// Read or modify runs of coefficients of all channels of a multichannel signal
+    /// for the same band and sample times, indexed by channel (see `MultiCoefs::process_slices`).
+    ///
+    /// By default each channel is passed to `process` separately.
+    /// Effects depending on several channels at once, like `spatial::Panner`, override this.
+    fn process_channels(&mut self, meta: SliceMeta, channels: &mut [&mut [Coef]]) {
+        for coefs in channels.iter_mut() {
+            self.process(meta, coefs);
+        }
+    }
+
+    /// Check that the effect can process coefficients of `n_channels` channels,
+    /// returning `InvalidInput` error otherwise. Called by `apply_effect` and similar methods before processing.
+    ///
+    /// Any number of channels is accepted by default.
+    /// Effects depending on several channels at once, like `spatial::Panner`, override this.
+    fn check_channels(&self, n_channels: usize) -> io::Result<()> {
+        let _ = n_channels;
+        Ok(())
+    }
+
+    /// Forget any state accumulated from processed coefficients, e.g. before processing unrelated signal.
+    fn reset(&mut self) {}
+}
+
+impl<E: SpectralEffect + ?Sized> SpectralEffect for Box<E> {
+    fn prepare(&mut self, g: &Gaborator) {
+        (**self).prepare(g)
+    }
+    fn process(&mut self, meta: SliceMeta, coefs: &mut [Coef]) {
+        (**self).process(meta, coefs)
+    }
+    fn process_channels(&mut self, meta: SliceMeta, channels: &mut [&mut [Coef]]) {
+        (**self).process_channels(meta, channels)
+    }
+    fn check_channels(&self, n_channels: usize) -> io::Result<()> {
+        (**self).check_channels(n_channels)
+    }
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+}
+
+/// Sequence of effects applied one after another to each run of coefficients.
+#[derive(Default)]
+pub struct Chain<'a> {
+    effects: Vec<Box<dyn SpectralEffect + 'a>>,
+}
+
+impl<'a> Chain<'a> {
+    /// Create empty chain, which leaves coefficients as is.
+    pub fn new() -> Self {
+        Chain { effects: Vec::new() }
+    }
+
+    /// Append an effect to the end of the chain.
+    pub fn push(&mut self, effect: impl SpectralEffect + 'a) {
+        self.effects.push(Box::new(effect));
+    }
+
+    /// Builder-style version of `push`.
+    pub fn with(mut self, effect: impl SpectralEffect + 'a) -> Self {
+        self.push(effect);
+        self
+    }
+
+    /// Number of effects in the chain.
+    pub fn len(&self) -> usize {
+        self.effects.len()
+    }
+
+    /// Whether the chain contains no effects.
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+}
+
+impl<'a> SpectralEffect for Chain<'a> {
+    fn prepare(&mut self, g: &Gaborator) {
+        for e in &mut self.effects {
+            e.prepare(g);
+        }
+    }
+    fn process(&mut self, meta: SliceMeta, coefs: &mut [Coef]) {
+        for e in &mut self.effects {
+            e.process(meta, coefs);
+        }
+    }
+    fn process_channels(&mut self, meta: SliceMeta, channels: &mut [&mut [Coef]]) {
+        for e in &mut self.effects {
+            e.process_channels(meta, channels);
+        }
+    }
+    fn check_channels(&self, n_channels: usize) -> io::Result<()> {
+        self.effects.iter().try_for_each(|e| e.check_channels(n_channels))
+    }
+    fn reset(&mut self) {
+        for e in &mut self.effects {
+            e.reset();
+        }
+    }
+}
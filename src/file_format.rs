@@ -0,0 +1,189 @@
+use std::io::{self, Read, Write};
+
+use crate::{Coef, Coefs, Gaborator, GaboratorParams};
+
+const MAGIC: &[u8; 8] = b"GABCOEFS";
+const VERSION: u32 = 1;
+
+const TAG_END: u8 = 0;
+const TAG_RUN: u8 = 1;
+
+/// Upper bound for number of coefficients in one run, to reject corrupt files early.
+/// Runs written by `save` never exceed a slice, which is much shorter.
+pub(crate) const MAX_RUN_LEN: u32 = 1 << 24;
+
+/// Upper bound for the time span covered by all coefficients of a file, in samples (about a day at 48 kHz).
+/// Gaborator indexes slices over the whole span, so corrupt times far apart would exhaust memory.
+const MAX_TIME_SPAN: i64 = 1 << 32;
+
+pub(crate) fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+pub(crate) fn read_array<const N: usize>(r: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_u8(r: &mut impl Read) -> io::Result<u8> {
+    Ok(read_array::<1>(r)?[0])
+}
+pub(crate) fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    Ok(u32::from_le_bytes(read_array(r)?))
+}
+pub(crate) fn read_i32(r: &mut impl Read) -> io::Result<i32> {
+    Ok(i32::from_le_bytes(read_array(r)?))
+}
+pub(crate) fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    Ok(u64::from_le_bytes(read_array(r)?))
+}
+pub(crate) fn read_i64(r: &mut impl Read) -> io::Result<i64> {
+    Ok(i64::from_le_bytes(read_array(r)?))
+}
+pub(crate) fn read_f32(r: &mut impl Read) -> io::Result<f32> {
+    Ok(f32::from_le_bytes(read_array(r)?))
+}
+fn read_f64(r: &mut impl Read) -> io::Result<f64> {
+    Ok(f64::from_le_bytes(read_array(r)?))
+}
+
+pub(crate) fn write_params(w: &mut impl Write, params: &GaboratorParams) -> io::Result<()> {
+    w.write_all(&params.bands_per_octave.to_le_bytes())?;
+    w.write_all(&params.ff_min.to_le_bytes())?;
+    w.write_all(&params.ff_ref.to_le_bytes())?;
+    w.write_all(&params.overlap.to_le_bytes())
+}
+
+pub(crate) fn read_params(r: &mut impl Read) -> io::Result<GaboratorParams> {
+    Ok(GaboratorParams {
+        bands_per_octave: read_u32(r)?,
+        ff_min: read_f64(r)?,
+        ff_ref: read_f64(r)?,
+        overlap: read_f64(r)?,
+    })
+}
+
+/// Check that data produced with `stored` parameters can be used with `g`
+pub(crate) fn check_params(stored: &GaboratorParams, g: &Gaborator) -> io::Result<()> {
+    if stored != g.params() {
+        return Err(invalid_data(format!(
+            "file was saved with parameters {:?}, but analyzer has {:?}",
+            stored,
+            g.params()
+        )));
+    }
+    Ok(())
+}
+
+/// Validation of coefficient runs read from a file, including the time span they cover together
+#[derive(Default)]
+pub(crate) struct RunChecker {
+    span: Option<(i64, i64)>,
+}
+
+impl RunChecker {
+    /// Check run of `len` coefficients of `band` starting at `sample_time`, returning its end time
+    pub(crate) fn check(&mut self, g: &Gaborator, band: i32, sample_time: i64, len: u32) -> io::Result<i64> {
+        if band < g.bandpass_bands_begin() || band > g.band_lowpass() {
+            return Err(invalid_data(format!("invalid band number {}", band)));
+        }
+        let step = g.band_time_step(band);
+        if sample_time % step != 0 {
+            return Err(invalid_data("coefficient time is not aligned to the band's time step"));
+        }
+        if len > MAX_RUN_LEN {
+            return Err(invalid_data("coefficient run is too long"));
+        }
+        let end_time = sample_time
+            .checked_add(len as i64 * step)
+            .ok_or_else(|| invalid_data("coefficient time is out of range"))?;
+
+        let (begin, end) = match self.span {
+            Some((begin, end)) => (begin.min(sample_time), end.max(end_time)),
+            None => (sample_time, end_time),
+        };
+        if end.checked_sub(begin).is_none_or(|span| span > MAX_TIME_SPAN) {
+            return Err(invalid_data("coefficients span too long a time"));
+        }
+        self.span = Some((begin, end));
+        Ok(end_time)
+    }
+}
+
+impl Coefs {
+    /// Save all existing coefficients to a compact binary file.
+    ///
+    /// The file begins with a versioned header containing the analyzer parameters,
+    /// followed by runs of coefficients of existing slices only.
+    /// Mutable access is needed only because of the way coefficients are read from Gaborator.
+    /// Writes many small chunks, so `w` should be buffered.
+    pub fn save(&mut self, mut w: impl Write) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&VERSION.to_le_bytes())?;
+        write_params(&mut w, &self.params)?;
+
+        let mut result: io::Result<()> = Ok(());
+        self.process_slices(i32::MIN, i32::MAX, i64::MIN, i64::MAX, |meta, coefs| {
+            if result.is_err() {
+                return;
+            }
+            result = (|| -> io::Result<()> {
+                w.write_all(&[TAG_RUN])?;
+                w.write_all(&meta.band.to_le_bytes())?;
+                w.write_all(&meta.sample_time.to_le_bytes())?;
+                w.write_all(&(coefs.len() as u32).to_le_bytes())?;
+                for c in coefs.iter() {
+                    w.write_all(&c.re.to_le_bytes())?;
+                    w.write_all(&c.im.to_le_bytes())?;
+                }
+                Ok(())
+            })();
+        });
+        result?;
+
+        w.write_all(&[TAG_END])?;
+        w.flush()
+    }
+
+    /// Load coefficients saved by `save`.
+    ///
+    /// Fails with `InvalidData` error if the file is not in the expected format,
+    /// was saved with parameters different from `g`'s, or its coefficients span more than 2^32 samples.
+    /// Reads many small chunks, so `r` should be buffered.
+    pub fn load(mut r: impl Read, g: &Gaborator) -> io::Result<Coefs> {
+        if &read_array::<8>(&mut r)? != MAGIC {
+            return Err(invalid_data("not a saved Gaborator coefficients file"));
+        }
+        let version = read_u32(&mut r)?;
+        if version != VERSION {
+            return Err(invalid_data(format!("unsupported file format version {}", version)));
+        }
+        check_params(&read_params(&mut r)?, g)?;
+
+        let mut coefs = Coefs::new(g);
+        let mut checker = RunChecker::default();
+        let mut run = Vec::new();
+        loop {
+            match read_u8(&mut r)? {
+                TAG_END => break,
+                TAG_RUN => (),
+                tag => return Err(invalid_data(format!("unexpected record tag {}", tag))),
+            }
+            let band = read_i32(&mut r)?;
+            let sample_time = read_i64(&mut r)?;
+            let len = read_u32(&mut r)?;
+            let end_time = checker.check(g, band, sample_time, len)?;
+            let step = g.band_time_step(band);
+
+            run.clear();
+            for _ in 0..len {
+                run.push(Coef::new(read_f32(&mut r)?, read_f32(&mut r)?));
+            }
+            coefs.fill(band, band + 1, sample_time, end_time, |meta, coef| {
+                *coef = run[((meta.sample_time - sample_time) / step) as usize];
+            });
+        }
+        Ok(coefs)
+    }
+}
@@ -0,0 +1,342 @@
+use std::ops::Range;
+
+use crate::{Coef, Coefs, Gaborator};
+
+/// Interpolation method for resampling coefficients of a band to a different time grid.
+///
+/// Gaborator's coefficients are already demodulated: a stationary sinusoid at a band's center frequency
+/// has constant phase in that band, so coefficients vary slowly and can be interpolated directly.
+///
+/// Gaborator's own `resample2.h` is not reused here: it only resamples by powers of two with
+/// an integer origin, while the grid hop is arbitrary, and it is meant for rendering spectrogram images
+/// (its header says it is not intended for audio), with a single fixed kernel.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Interp {
+    /// Take the coefficient closest in time
+    Nearest,
+    /// Linear interpolation between two adjacent coefficients
+    Linear,
+    /// Lanczos interpolation with four nearest coefficients (`a = 2`)
+    Lanczos,
+}
+
+impl Interp {
+    /// Interpolate `values` at fractional index `x`. Indices outside of `values` are clamped to its ends.
+    fn interpolate(self, values: &[Coef], x: f64) -> Coef {
+        let n = values.len() as i64;
+        if n == 0 {
+            return Coef::default();
+        }
+        let at = |k: i64| values[k.max(0).min(n - 1) as usize];
+        let base = x.floor();
+        let k0 = base as i64;
+        let frac = x - base;
+        match self {
+            Interp::Nearest => at(x.round() as i64),
+            Interp::Linear => {
+                let (a, b) = (at(k0), at(k0 + 1));
+                a + (b - a) * frac as f32
+            }
+            Interp::Lanczos => {
+                let mut re = 0.0;
+                let mut im = 0.0;
+                let mut total = 0.0;
+                for k in k0 - 1..=k0 + 2 {
+                    let w = lanczos2(x - k as f64);
+                    let c = at(k);
+                    re += c.re as f64 * w;
+                    im += c.im as f64 * w;
+                    total += w;
+                }
+                Coef {
+                    re: (re / total) as f32,
+                    im: (im / total) as f32,
+                }
+            }
+        }
+    }
+}
+
+fn lanczos2(x: f64) -> f64 {
+    let sinc = |x: f64| {
+        if x == 0.0 {
+            1.0
+        } else {
+            let px = std::f64::consts::PI * x;
+            px.sin() / px
+        }
+    };
+    if x.abs() >= 2.0 {
+        0.0
+    } else {
+        sinc(x) * sinc(x / 2.0)
+    }
+}
+
+/// Read coefficients number `ci0..ci1` of `band` (sample times `ci * time_step`).
+/// Missing coefficients read as zero.
+pub(crate) fn read_band(coefs: &mut Coefs, band: i32, time_step: i64, ci0: i64, ci1: i64) -> Vec<Coef> {
+    let mut buf = vec![Coef::default(); (ci1 - ci0).max(0) as usize];
+    coefs.process_slices(band, band + 1, ci0 * time_step, ci1 * time_step, |meta, cs| {
+        let offset = (meta.sample_time / time_step - ci0) as usize;
+        buf[offset..offset + cs.len()].copy_from_slice(cs);
+    });
+    buf
+}
+
+/// Clip `band_range` to bands that exist in `g`.
+pub(crate) fn clip_bands(g: &Gaborator, band_range: Range<i32>) -> Range<i32> {
+    let begin = band_range.start.max(g.bandpass_bands_begin());
+    let end = band_range.end.min(g.band_lowpass() + 1);
+    begin..end.max(begin)
+}
+
+/// Number of frames every `hop` samples starting from `time_range.start` and not reaching `time_range.end`
+fn frame_count(hop: u32, time_range: &Range<i64>) -> usize {
+    if time_range.end > time_range.start {
+        ((time_range.end - time_range.start - 1) / hop as i64 + 1) as usize
+    } else {
+        0
+    }
+}
+
+/// Dense bands × frames matrix of values sampled on a uniform time grid.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "GridFields<T>"))]
+pub struct Grid<T> {
+    /// Band number of the first row
+    pub band_begin: i32,
+    /// Number of rows
+    pub n_bands: usize,
+    /// Sample time of the first column
+    pub time_begin: i64,
+    /// Distance between points in time of adjacent columns, in samples
+    pub hop: u32,
+    /// Number of columns
+    pub n_frames: usize,
+    /// Values in row-major order: `n_frames` values for `band_begin`, then for `band_begin + 1` and so on.
+    pub data: Vec<T>,
+}
+
+/// `Grid` as deserialized, before checking that `data` matches its dimensions
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct GridFields<T> {
+    band_begin: i32,
+    n_bands: usize,
+    time_begin: i64,
+    hop: u32,
+    n_frames: usize,
+    data: Vec<T>,
+}
+
+#[cfg(feature = "serde")]
+impl<T> std::convert::TryFrom<GridFields<T>> for Grid<T> {
+    type Error = String;
+
+    fn try_from(g: GridFields<T>) -> Result<Self, String> {
+        if g.n_bands.checked_mul(g.n_frames) != Some(g.data.len()) {
+            return Err(format!(
+                "grid of {} bands and {} frames can't have {} values",
+                g.n_bands,
+                g.n_frames,
+                g.data.len()
+            ));
+        }
+        Ok(Grid {
+            band_begin: g.band_begin,
+            n_bands: g.n_bands,
+            time_begin: g.time_begin,
+            hop: g.hop,
+            n_frames: g.n_frames,
+            data: g.data,
+        })
+    }
+}
+
+impl<T> Grid<T> {
+    /// One past the band number of the last row
+    pub fn band_end(&self) -> i32 {
+        self.band_begin + self.n_bands as i32
+    }
+
+    /// Sample time of column number `frame`
+    pub fn frame_time(&self, frame: usize) -> i64 {
+        self.time_begin + frame as i64 * self.hop as i64
+    }
+
+    /// Values of band number `band` for all frames.
+    /// Panics if the band is not within the grid.
+    pub fn row(&self, band: i32) -> &[T] {
+        let r = self.row_index(band);
+        &self.data[r * self.n_frames..(r + 1) * self.n_frames]
+    }
+
+    /// Mutable version of `row`
+    pub fn row_mut(&mut self, band: i32) -> &mut [T] {
+        let r = self.row_index(band);
+        &mut self.data[r * self.n_frames..(r + 1) * self.n_frames]
+    }
+
+    /// Value of band number `band` at column number `frame`
+    pub fn get(&self, band: i32, frame: usize) -> &T {
+        &self.row(band)[frame]
+    }
+
+    /// Convert each value using `f`, keeping the grid layout.
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {
+            band_begin: self.band_begin,
+            n_bands: self.n_bands,
+            time_begin: self.time_begin,
+            hop: self.hop,
+            n_frames: self.n_frames,
+            data: self.data.iter().map(f).collect(),
+        }
+    }
+
+    fn row_index(&self, band: i32) -> usize {
+        assert!(
+            band >= self.band_begin && band < self.band_end(),
+            "band {} is outside of the grid",
+            band
+        );
+        (band - self.band_begin) as usize
+    }
+}
+
+impl Grid<Coef> {
+    /// Magnitudes of the coefficients
+    pub fn magnitudes(&self) -> Grid<f32> {
+        self.map(|c| c.norm())
+    }
+}
+
+impl Coefs {
+    /// Resample coefficients of bands within `band_range` to a uniform time grid:
+    /// one column every `hop` samples, starting from `time_range.start` and not reaching `time_range.end`.
+    ///
+    /// `band_range` is clipped to the bands that exist, so `i32::MIN..i32::MAX` means all bands.
+    /// Missing coefficients read as zero.
+    pub fn to_grid(
+        &mut self,
+        g: &Gaborator,
+        hop: u32,
+        time_range: Range<i64>,
+        band_range: Range<i32>,
+        interp: Interp,
+    ) -> Grid<Coef> {
+        assert!(hop > 0, "hop should be positive");
+        let bands = clip_bands(g, band_range);
+        let n_frames = frame_count(hop, &time_range);
+        let mut grid = Grid {
+            band_begin: bands.start,
+            n_bands: bands.len(),
+            time_begin: time_range.start,
+            hop,
+            n_frames,
+            data: Vec::with_capacity(bands.len() * n_frames),
+        };
+        if n_frames == 0 {
+            return grid;
+        }
+        let last_time = grid.frame_time(n_frames - 1);
+        for band in bands {
+            let step = g.band_time_step(band);
+            let ci0 = time_range.start.div_euclid(step) - 2;
+            let ci1 = last_time.div_euclid(step) + 3;
+            let values = read_band(self, band, step, ci0, ci1);
+            for frame in 0..n_frames {
+                let x = grid.frame_time(frame) as f64 / step as f64 - ci0 as f64;
+                grid.data.push(interp.interpolate(&values, x));
+            }
+        }
+        grid
+    }
+
+    /// Like `to_grid`, but returns magnitudes of the resampled coefficients.
+    pub fn to_magnitude_grid(
+        &mut self,
+        g: &Gaborator,
+        hop: u32,
+        time_range: Range<i64>,
+        band_range: Range<i32>,
+        interp: Interp,
+    ) -> Grid<f32> {
+        self.to_grid(g, hop, time_range, band_range, interp).magnitudes()
+    }
+
+    /// Mean energy (squared magnitude) of coefficients of bands within `band_range` over frames of `hop` samples
+    /// centered on `time_range.start`, `time_range.start + hop` and so on, not reaching `time_range.end`.
+    ///
+    /// Unlike `to_grid`, which samples coefficients at frame times, each coefficient's energy is spread over
+    /// the time step it represents, so no energy is missed or counted twice for any `hop`.
+    pub fn to_energy_grid(
+        &mut self,
+        g: &Gaborator,
+        hop: u32,
+        time_range: Range<i64>,
+        band_range: Range<i32>,
+    ) -> Grid<f32> {
+        assert!(hop > 0, "hop should be positive");
+        let bands = clip_bands(g, band_range);
+        let n_frames = frame_count(hop, &time_range);
+        let mut grid = Grid {
+            band_begin: bands.start,
+            n_bands: bands.len(),
+            time_begin: time_range.start,
+            hop,
+            n_frames,
+            data: vec![0.0; bands.len() * n_frames],
+        };
+        if n_frames == 0 || bands.is_empty() {
+            return grid;
+        }
+
+        let hop = hop as i64;
+        let frames_begin = time_range.start - hop / 2;
+        let frames_end = frames_begin + n_frames as i64 * hop;
+        // Coefficients slightly outside of the frames still cover them partially
+        let margin = g.band_time_step(bands.end - 1);
+        self.process_slices(bands.start, bands.end, frames_begin - margin, frames_end + margin, |meta, cs| {
+            let row = grid.row_mut(meta.band);
+            let step = meta.time_step as i64;
+            for (i, c) in cs.iter().enumerate() {
+                let energy = c.norm_sqr();
+                if energy == 0.0 {
+                    continue;
+                }
+                // Interval of time this coefficient represents, clipped to the frames
+                let t = meta.sample_time + i as i64 * step;
+                let mut start = (t - step / 2).max(frames_begin);
+                let t1 = (t - step / 2 + step).min(frames_end);
+                while start < t1 {
+                    let f = ((start - frames_begin) / hop) as usize;
+                    let end = t1.min(frames_begin + (f as i64 + 1) * hop);
+                    row[f] += energy * (end - start) as f32 / hop as f32;
+                    start = end;
+                }
+            }
+        });
+        grid
+    }
+
+    /// Inverse of `to_grid`: resample each row of `grid` back to the band's own time step
+    /// and write the values using `fill`, replacing coefficients within the grid's time span
+    /// (`grid.time_begin` to `grid.time_begin + grid.n_frames * grid.hop`).
+    pub fn fill_from_grid(&mut self, g: &Gaborator, grid: &Grid<Coef>, interp: Interp) {
+        if grid.n_frames == 0 {
+            return;
+        }
+        let time_end = grid.time_begin + grid.n_frames as i64 * grid.hop as i64;
+        for band in clip_bands(g, grid.band_begin..grid.band_end()) {
+            let values = grid.row(band);
+            self.fill(band, band + 1, grid.time_begin, time_end, |meta, coef| {
+                let x = (meta.sample_time - grid.time_begin) as f64 / grid.hop as f64;
+                *coef = interp.interpolate(values, x);
+            });
+        }
+    }
+}
@@ -13,14 +13,43 @@
 //! * No visualisation
 //! * Crate soundness may be iffy - I was just followed the path of least resistance.
 //! * Arithmentic overflows in buffer length calculations are not checked.
-//! * Not really tested, apart from included examples. For example, streaming should be supported, but I haven't tried it myself.
+//! * Not really tested, apart from included examples.
+//!
+//! Parameters can be specified in Hz using [`GaboratorParams::for_sample_rate`]. Analyzer created with [`Gaborator::with_sample_rate`] also offers band frequencies in Hz and time in seconds; for other analyzers these accessors return `None`, and analyses needing them (`pitch`, `onset`, `beat`, `transcribe`, `chroma`) return an `InvalidInput` error.
+//! `notes` module maps bands to MIDI notes with cents offsets and note names.
+//! Pitch class profiles for harmony analysis are computed by [`chroma`] module.
+//! Fundamental frequency of monophonic signals is tracked by `pitch` module, with voicing confidence and octave error correction.
+//! Polyphonic notes can be transcribed by `transcribe` module and written as Standard MIDI Files by `midi` module.
+//! Onsets are detected by `onset` module from spectral flux or complex-domain novelty with adaptive peak picking.
+//! Global tempo and beat times are estimated from the onset novelty by `beat` module.
+//!
+//! Streaming analysis of unbounded input is available using [`StreamingAnalyzer`].
+//!
+//! Multichannel signals can be analyzed into [`MultiCoefs`] from interleaved frames using [`Gaborator::analyze_interleaved`].
+//! For stereo, `spatial` module computes per-coefficient pan position, level and phase differences, and provides a spectral panner.
+//!
+//! Coefficients can be resampled to a uniform time grid using [`Coefs::to_grid`].
+//! With `ndarray` feature enabled, such grids can be converted to and from `ndarray` arrays.
+//! Grids can also be written to NumPy `.npy` or `.npz` files (with band frequency and time axes) using [`Grid::write_npz`].
+//!
+//! Signals can be made longer or shorter without changing pitch by `stretch` module, which keeps transients sharp.
+//! Pitch can be shifted without changing duration by `shift` module, optionally keeping formants in place.
+//!
+//! Reusable spectral effects can implement [`SpectralEffect`] and be combined using [`Chain`].
+//!
+//! Coefficients can be saved losslessly using [`Coefs::save`], or compressed with a lossy codec using [`Coefs::save_lossy`] (see `codec` module).
+//!
+//! With `wav` feature enabled, `wav` module reads and writes WAV files of any channel count, bit depth and sample rate.
 //!
 //! Currently based on Gaborator version 1.6. Source code of the Gaborator is included into the crate.
 //! 
-//! Availble examples:
+//! Availble examples (require `wav` feature):
 //! 
-//! * Phase information randomizer, creating sort-of-reverberation audio effect.
-//! * Converts the analyzed sound to (sample,band,magnitude,phase) CSV file and back.
+//! * Phase information randomizer, creating sort-of-reverberation audio effect. Processes input in blocks using `StreamingProcessor`.
+//! * Converts the analyzed sound to (sample,band,magnitude,phase) CSV file and back, using `csv` module.
+//! * Compresses the analyzed sound with the lossy codec at a few quality settings and reports size and SNR.
+//! * Prints pitch track of a monophonic sound with note names, using `pitch` module.
+//! * Transcribes notes of the analyzed sound to a MIDI file.
 //!
 //! License of Gaborator is Affero GPL 3.0.
 //!
@@ -29,25 +58,86 @@
 
 #![deny(missing_docs)]
 
-pub use gaborator_sys::{Coef, CoefMeta, Params as GaboratorParams};
+use std::io;
+
+pub use gaborator_sys::{Coef, CoefMeta, SliceMeta, Params as GaboratorParams};
+
+mod cache;
+pub use cache::GaboratorCache;
+
+mod streaming;
+pub use streaming::{StreamingAnalyzer, StreamingProcessor};
+
+mod effect;
+pub use effect::{Chain, SpectralEffect};
+
+mod grid;
+pub use grid::{Grid, Interp};
+
+#[cfg(feature = "ndarray")]
+mod array;
+
+mod units;
+mod multi;
+pub use multi::MultiCoefs;
+
+mod file_format;
+pub mod codec;
+pub mod csv;
+pub mod spatial;
+pub mod notes;
+pub mod chroma;
+pub mod pitch;
+pub mod transcribe;
+pub mod midi;
+pub mod onset;
+pub mod beat;
+pub mod stretch;
+pub mod shift;
+#[cfg(feature = "wav")]
+pub mod wav;
+mod npy;
+pub use npy::{write_npy, NpyElement};
 
+/// Gaborator uses non-atomic reference counts (shared between an analyzer and all its `Coefs`)
+/// and a global FFT pool, so creating or destroying analyzers and coefficient sets is serialized here.
+static LIFECYCLE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+fn lifecycle_lock() -> std::sync::MutexGuard<'static, ()> {
+    LIFECYCLE_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Gaborator reports failure to allocate memory with `std::bad_alloc`, which arrives here as `Err`.
+/// Like Rust's own collections, give up then, but by panicking instead of aborting.
+fn expect_memory<T>(result: Result<T, gaborator_sys::cxx::Exception>) -> T {
+    result.unwrap_or_else(|e| panic!("Gaborator failed to allocate memory: {}", e))
+}
 
 /// Reprepresents C++'s `gaborator::coefs<float>`
 /// Can be memory-hungry.
+/// Also remembers parameters of the `Gaborator` it was created for.
 /// 
 /// (I'm not sure whether this can be dropped after `Analyzer`.
 /// I see some mention of reference counting withing Gaborator library,
 /// but have not checked in detail.)
-pub struct Coefs(gaborator_sys::cxx::UniquePtr<gaborator_sys::Coefs>);
+pub struct Coefs {
+    ptr: gaborator_sys::cxx::UniquePtr<gaborator_sys::Coefs>,
+    params: GaboratorParams,
+}
 
 impl Coefs {
     /// Create new instance of Gaborator analyzer/synthesizer based on supplied parameters
     pub fn new(gab: &Gaborator) -> Self {
-        Coefs(
-            gaborator_sys::create_coefs(&*gab.0)
-        )
+        let _guard = lifecycle_lock();
+        Coefs {
+            ptr: expect_memory(gaborator_sys::create_coefs(&gab.ptr)),
+            params: gab.params,
+        }
     }
 
+    /// Parameters of the `Gaborator` these coefficients were created for
+    pub fn params(&self) -> &GaboratorParams { &self.params }
+
     /// Allow the coefficients for points in time before limit 
     /// (a time in units of samples) to be forgotten.
     /// Streaming applications can use this to free memory used by coefficients
@@ -57,12 +147,12 @@ impl Coefs {
     /// any remaining coefficients before limit is bounded.
     pub fn forget_before(&mut self, g:&Gaborator, limit: i64, clean_cut: bool)
     {
-        gaborator_sys::forget_before(
-            &*g.0,
-            self.0.pin_mut(),
+        expect_memory(gaborator_sys::forget_before(
+            &g.ptr,
+            self.ptr.pin_mut(),
             limit,
             clean_cut,
-        )
+        ))
     }
 
     /// Read or write values within `Coefs`, skipping over non-existent entries.
@@ -78,7 +168,7 @@ impl Coefs {
         callback: impl FnMut(CoefMeta, &mut Coef),
     ) {
         gaborator_sys::process(
-            self.0.pin_mut(),
+            self.ptr.pin_mut(),
             from_band,
             to_band,
             from_sample_time,
@@ -87,6 +177,47 @@ impl Coefs {
         )
     }
 
+    /// Like `process`, but the callback receives runs of consecutive coefficients of one band at once.
+    /// Corresponds to `apply_to_slice` helper function of Gaborator.
+    pub fn process_slices(
+        &mut self,
+        from_band: i32,
+        to_band: i32,
+        from_sample_time: i64,
+        to_sample_time: i64,
+        mut callback: impl FnMut(SliceMeta, &mut [Coef]),
+    ) {
+        gaborator_sys::process_slices(
+            self.ptr.pin_mut(),
+            from_band,
+            to_band,
+            from_sample_time,
+            to_sample_time,
+            &mut gaborator_sys::ProcessSlicesCallback(&mut callback),
+        )
+    }
+
+    /// Apply `effect` to all existing coefficients.
+    /// The effect should have been prepared using the same `Gaborator`.
+    /// Fails if the effect does not work on a single channel, see `SpectralEffect::check_channels`.
+    pub fn apply_effect(&mut self, effect: &mut (impl SpectralEffect + ?Sized)) -> io::Result<()> {
+        self.apply_effect_range(i64::MIN, i64::MAX, effect)
+    }
+
+    /// Apply `effect` to existing coefficients within the given time range.
+    /// The effect should have been prepared using the same `Gaborator`.
+    /// Fails if the effect does not work on a single channel, see `SpectralEffect::check_channels`.
+    pub fn apply_effect_range(
+        &mut self,
+        from_sample_time: i64,
+        to_sample_time: i64,
+        effect: &mut (impl SpectralEffect + ?Sized),
+    ) -> io::Result<()> {
+        effect.check_channels(1)?;
+        self.process_slices(i32::MIN, i32::MAX, from_sample_time, to_sample_time, |meta, coefs| effect.process(meta, coefs));
+        Ok(())
+    }
+
     /// Write values to `Coefs`, creating non-existent entries as needed.
     /// Corresponds to `fill` function of Gaborator.
     /// `from_band` and `to_band` may be given INT_MIN / INT_MAX values, that would mean all bands.
@@ -99,41 +230,89 @@ impl Coefs {
         to_sample_time: i64,
         callback: impl FnMut(CoefMeta, &mut Coef),
     ) {
-        gaborator_sys::fill(
-            self.0.pin_mut(),
+        expect_memory(gaborator_sys::fill(
+            self.ptr.pin_mut(),
             from_band,
             to_band,
             from_sample_time,
             to_sample_time,
             &mut gaborator_sys::ProcessOrFillCallback(Box::new(callback)),
-        )
+        ))
     }
 }
 
+impl Drop for Coefs {
+    fn drop(&mut self) {
+        let _guard = lifecycle_lock();
+        drop(std::mem::replace(&mut self.ptr, gaborator_sys::cxx::UniquePtr::null()));
+    }
+}
+
+// Reference count manipulations are guarded by `LIFECYCLE_LOCK`, everything else touches only this object.
+unsafe impl Send for Coefs {}
+
 
 /// Main type of the crate. Represents C++'s `gaborator::analyzer<float>`.
-pub struct Gaborator(gaborator_sys::cxx::UniquePtr<gaborator_sys::Analyzer>);
+///
+/// Can be shared between threads, e.g. using [`GaboratorCache`].
+/// Optionally remembers the sample rate of the signal, enabling accessors in Hz and seconds.
+pub struct Gaborator {
+    ptr: gaborator_sys::cxx::UniquePtr<gaborator_sys::Analyzer>,
+    params: GaboratorParams,
+    sample_rate: Option<f64>,
+}
+
+impl Drop for Gaborator {
+    fn drop(&mut self) {
+        let _guard = lifecycle_lock();
+        drop(std::mem::replace(&mut self.ptr, gaborator_sys::cxx::UniquePtr::null()));
+    }
+}
+
+// Analysis and synthesis are `const` methods in C++ that only read filter bank and FFT tables,
+// using temporary buffers of their own; shared FFTs are taken from `pool<>::shared` only while
+// constructing the analyzer. Reference counts shared with `Coefs` change only when analyzers
+// or coefficient sets are created or destroyed, which is guarded by `LIFECYCLE_LOCK`.
+// See `tests/concurrency.rs`.
+unsafe impl Send for Gaborator {}
+unsafe impl Sync for Gaborator {}
 
 
 impl Gaborator {
     /// Create new instance of Gaborator analyzer/synthesizer based on supplied parameters
     pub fn new(params: &GaboratorParams) -> Self {
-        Gaborator(
-            gaborator_sys::new_analyzer(params)
-        )
+        let _guard = lifecycle_lock();
+        Gaborator {
+            ptr: expect_memory(gaborator_sys::new_analyzer(params)),
+            params: *params,
+            sample_rate: None,
+        }
     }
 
+    /// Like `new`, but also remember sample rate of the signal (in Hz), e.g. for `band_hz`.
+    pub fn with_sample_rate(params: &GaboratorParams, sample_rate: f64) -> Self {
+        let mut g = Gaborator::new(params);
+        g.sample_rate = Some(sample_rate);
+        g
+    }
+
+    /// Parameters this analyzer was created with
+    pub fn params(&self) -> &GaboratorParams { &self.params }
+
+    /// Sample rate in Hz, if the analyzer was created using `with_sample_rate`
+    pub fn sample_rate(&self) -> Option<f64> { self.sample_rate }
+
     /// Returns the one-sided worst-case time domain support of any of the analysis filters.
     /// When calling `analyze()` with a sample at time t, only spectrogram coefficients within
     /// the time range t ± support will be significantly changed. Coefficients outside the range
     /// may change, but the changes will sufficiently small that they may be ignored without significantly reducing accuracy.
-    pub fn analysis_support_len(&self) -> usize { gaborator_sys::get_analysis_support_len(&*self.0) }
+    pub fn analysis_support_len(&self) -> usize { gaborator_sys::get_analysis_support_len(&self.ptr) }
 
     /// Returns the one-sided worst-case time domain support of any of the reconstruction filters.
     /// When calling synthesize() to synthesize a sample at time t, the sample will only be significantly
     /// affected by spectrogram coefficients in the time range t ± support. Coefficients outside the range
     /// may be used in the synthesis, but substituting zeroes for the actual coefficient values will not significantly reduce accuracy.
-    pub fn synthesis_support_len(&self) -> usize { gaborator_sys::get_synthesis_support_len(&*self.0) }
+    pub fn synthesis_support_len(&self) -> usize { gaborator_sys::get_synthesis_support_len(&self.ptr) }
 
     /// Return the smallest valid bandpass band number, corresponding to the highest-frequency bandpass filter.
     /// 
@@ -141,21 +320,25 @@ impl Gaborator {
     /// increase towards lower (sic) frequencies. There is a number of bandpass bands corresponding
     /// to the logarithmically spaced bandpass analysis filters, from near 0.5 (half the sample rate)
     /// to near fmin, and a single lowpass band containing the residual signal from frequencies below fmin.
-    pub fn  bandpass_bands_begin(&self) -> i32 { gaborator_sys::bandpass_bands_begin(&*self.0) }
+    pub fn  bandpass_bands_begin(&self) -> i32 { gaborator_sys::bandpass_bands_begin(&self.ptr) }
 
     /// Return the bandpass band number one past the highest valid bandpass band number,
     /// corresponding to one past the lowest-frequency bandpass filter. 
-    pub fn  bandpass_bands_end(&self) -> i32 { gaborator_sys::bandpass_bands_end(&*self.0) }
+    pub fn  bandpass_bands_end(&self) -> i32 { gaborator_sys::bandpass_bands_end(&self.ptr) }
 
     /// Return the band number of the lowpass band. 
-    pub fn  band_lowpass(&self)  -> i32 { gaborator_sys::band_lowpass(&*self.0) }
+    pub fn  band_lowpass(&self)  -> i32 { gaborator_sys::band_lowpass(&self.ptr) }
 
     /// Return the band number corresponding to the reference frequency `ff_ref`.
     /// If `ff_ref` falls within the frequency range of the bandpass filter bank, this will be a valid bandpass band number, otherwise it will not. 
-    pub fn  band_ref(&self) -> i32 { gaborator_sys::band_ref(&*self.0) }
+    pub fn  band_ref(&self) -> i32 { gaborator_sys::band_ref(&self.ptr) }
 
     /// Return the center frequency of band number `band`, in units of the sampling frequency. 
-    pub fn  band_ff(&self, band: i32) -> f64 { gaborator_sys::band_ff(&*self.0, band) }
+    pub fn  band_ff(&self, band: i32) -> f64 { gaborator_sys::band_ff(&self.ptr, band) }
+
+    /// Return the distance between points in time of adjacent coefficients of band number `band`, in samples.
+    /// This is always a power of two, and is larger for lower-frequency bands.
+    pub fn  band_time_step(&self, band: i32) -> i64 { 1 << gaborator_sys::band_step_log2(&self.ptr, band) }
 
     /// Spectrum analyze the samples at `signal` and add the resulting coefficients to `coefs`.
     /// `t1` parameter from Gaborator's `analyze` method is caluclated based on supplied slice size.
@@ -167,12 +350,12 @@ impl Gaborator {
         signal_begin_sample_number: i64,
         coefs: &mut Coefs,
     ) {
-        gaborator_sys::analyze(
-            &*self.0,
+        expect_memory(gaborator_sys::analyze(
+            &self.ptr,
             signal,
             signal_begin_sample_number,
-            coefs.0.pin_mut(),
-        )
+            coefs.ptr.pin_mut(),
+        ))
     }
         
     /// Synthesize signal samples from the coefficients `coef` and store them at `signal`. 
@@ -186,11 +369,11 @@ impl Gaborator {
         signal_begin_sample_number: i64,
         signal: &mut [f32],
     ) {
-        gaborator_sys::synthesize(
-            &*self.0,
-            &*coefs.0,
+        expect_memory(gaborator_sys::synthesize(
+            &self.ptr,
+            &coefs.ptr,
             signal_begin_sample_number,
             signal,
-        )
+        ))
     }
 }
\ No newline at end of file
@@ -0,0 +1,86 @@
+//! Writing note events (see `transcribe` module) as Standard MIDI Files.
+//!
+//! Files are of format 0 (single track), with all notes on channel 1, at 120 beats per minute
+//! and `TICKS_PER_QUARTER` ticks per beat, so one tick is about a millisecond.
+
+use std::io::{self, Write};
+
+use crate::transcribe::NoteEvent;
+
+/// Time resolution of written files: number of ticks per quarter note (beat)
+pub const TICKS_PER_QUARTER: u16 = 480;
+/// Tempo of written files, in microseconds per quarter note (120 beats per minute)
+pub const TEMPO: u32 = 500_000;
+
+const NOTE_OFF: u8 = 0x80;
+const NOTE_ON: u8 = 0x90;
+
+/// Largest variable-length quantity allowed by the format (4 bytes), which bounds event times in ticks
+const MAX_VLQ: u32 = 0x0FFF_FFFF;
+
+/// Append variable-length quantity: 7 bits per byte, most significant first, high bit set on all but the last byte
+fn write_vlq(out: &mut Vec<u8>, mut value: u32) {
+    debug_assert!(value <= MAX_VLQ, "variable-length quantity {} is out of range", value);
+    let mut buf = [0u8; 5];
+    let mut i = buf.len() - 1;
+    buf[i] = (value & 0x7F) as u8;
+    value >>= 7;
+    while value > 0 {
+        i -= 1;
+        buf[i] = (value & 0x7F) as u8 | 0x80;
+        value >>= 7;
+    }
+    out.extend_from_slice(&buf[i..]);
+}
+
+/// Write `notes` as a Standard MIDI File. Their sample times are converted to ticks using `sample_rate`;
+/// notes starting before sample time 0 are moved to 0, and notes shorter than a tick are extended to one tick.
+/// Times past the largest one the format can store (about 37 hours) are moved to it.
+pub fn write_midi(mut w: impl Write, notes: &[NoteEvent], sample_rate: f64) -> io::Result<()> {
+    let ticks_per_sample = TICKS_PER_QUARTER as f64 * 1e6 / TEMPO as f64 / sample_rate;
+    let to_ticks = |t: i64| (t as f64 * ticks_per_sample).round().clamp(0.0, MAX_VLQ as f64) as u32;
+
+    // (tick, note off first, status, key, velocity)
+    let mut events: Vec<(u32, bool, u8, u8, u8)> = Vec::with_capacity(notes.len() * 2);
+    for n in notes {
+        if !(0..128).contains(&n.note.midi) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("MIDI note {} is out of range", n.note.midi),
+            ));
+        }
+        let key = n.note.midi as u8;
+        // Note off comes first within a tick (ending the previous note of the same key),
+        // so a note needs at least one tick not to end before it starts
+        let onset = to_ticks(n.onset).min(MAX_VLQ - 1);
+        let offset = to_ticks(n.offset).max(onset + 1);
+        events.push((onset, false, NOTE_ON, key, n.velocity.clamp(1, 127)));
+        events.push((offset, true, NOTE_OFF, key, 64));
+    }
+    events.sort_by_key(|(tick, off, ..)| (*tick, !*off));
+
+    let mut track = Vec::new();
+    // Tempo meta event
+    write_vlq(&mut track, 0);
+    track.extend_from_slice(&[0xFF, 0x51, 0x03]);
+    track.extend_from_slice(&TEMPO.to_be_bytes()[1..]);
+    let mut last_tick = 0;
+    for (tick, _, status, key, velocity) in events {
+        write_vlq(&mut track, tick - last_tick);
+        track.extend_from_slice(&[status, key, velocity]);
+        last_tick = tick;
+    }
+    // End of track meta event
+    write_vlq(&mut track, 0);
+    track.extend_from_slice(&[0xFF, 0x2F, 0x00]);
+
+    w.write_all(b"MThd")?;
+    w.write_all(&6u32.to_be_bytes())?;
+    w.write_all(&0u16.to_be_bytes())?;
+    w.write_all(&1u16.to_be_bytes())?;
+    w.write_all(&TICKS_PER_QUARTER.to_be_bytes())?;
+    w.write_all(b"MTrk")?;
+    w.write_all(&(track.len() as u32).to_be_bytes())?;
+    w.write_all(&track)?;
+    Ok(())
+}
@@ -0,0 +1,208 @@
+use std::io;
+
+use crate::{Coef, CoefMeta, Coefs, Gaborator, SliceMeta, SpectralEffect};
+
+/// Coefficients of a multichannel signal: one `Coefs` per channel, all created for the same `Gaborator`.
+pub struct MultiCoefs(Vec<Coefs>);
+
+impl MultiCoefs {
+    /// Create empty coefficients for `n_channels` channels
+    pub fn new(gab: &Gaborator, n_channels: usize) -> Self {
+        MultiCoefs((0..n_channels).map(|_| Coefs::new(gab)).collect())
+    }
+
+    /// Combine coefficients of separate channels.
+    /// Panics if they were created for analyzers with different parameters.
+    pub fn from_channels(channels: Vec<Coefs>) -> Self {
+        if let Some((first, rest)) = channels.split_first() {
+            assert!(
+                rest.iter().all(|c| c.params() == first.params()),
+                "all channels should be created for the same analyzer parameters"
+            );
+        }
+        MultiCoefs(channels)
+    }
+
+    /// Split into coefficients of separate channels
+    pub fn into_channels(self) -> Vec<Coefs> {
+        self.0
+    }
+
+    /// Number of channels
+    pub fn n_channels(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Coefficients of all channels
+    pub fn channels(&self) -> &[Coefs] {
+        &self.0
+    }
+
+    /// Mutable version of `channels`
+    pub fn channels_mut(&mut self) -> &mut [Coefs] {
+        &mut self.0
+    }
+
+    /// Coefficients of channel number `channel`
+    pub fn channel(&self, channel: usize) -> &Coefs {
+        &self.0[channel]
+    }
+
+    /// Mutable version of `channel`
+    pub fn channel_mut(&mut self, channel: usize) -> &mut Coefs {
+        &mut self.0[channel]
+    }
+
+    /// `Coefs::forget_before` for each channel
+    pub fn forget_before(&mut self, g: &Gaborator, limit: i64, clean_cut: bool) {
+        for c in &mut self.0 {
+            c.forget_before(g, limit, clean_cut);
+        }
+    }
+
+    /// Like `Coefs::process_slices`, but the callback receives runs of coefficients of all channels
+    /// for the same band and sample times at once, indexed by channel.
+    ///
+    /// Runs are those existing in the first channel. Missing coefficients of other channels read as zero
+    /// and are created when written back.
+    pub fn process_slices(
+        &mut self,
+        from_band: i32,
+        to_band: i32,
+        from_sample_time: i64,
+        to_sample_time: i64,
+        mut callback: impl FnMut(SliceMeta, &mut [&mut [Coef]]),
+    ) {
+        let (first, rest) = match self.0.split_first_mut() {
+            Some(x) => x,
+            None => return,
+        };
+        // Runs of the other channels are copied through buffers reused for all runs
+        let mut others: Vec<Vec<Coef>> = (0..rest.len()).map(|_| Vec::new()).collect();
+        let mut all_buf: Vec<&mut [Coef]> = Vec::with_capacity(rest.len() + 1);
+        first.process_slices(from_band, to_band, from_sample_time, to_sample_time, |meta, coefs| {
+            let step = meta.time_step as i64;
+            let ci0 = meta.sample_time / step;
+            let time_end = meta.sample_time + coefs.len() as i64 * step;
+            for (c, values) in rest.iter_mut().zip(&mut others) {
+                values.clear();
+                values.resize(coefs.len(), Coef::default());
+                c.process_slices(meta.band, meta.band + 1, meta.sample_time, time_end, |m, cs| {
+                    let offset = (m.sample_time / step - ci0) as usize;
+                    values[offset..offset + cs.len()].copy_from_slice(cs);
+                });
+            }
+
+            let mut all = recycle(std::mem::take(&mut all_buf));
+            all.push(coefs);
+            all.extend(others.iter_mut().map(|o| &mut o[..]));
+            callback(meta, &mut all);
+            all_buf = recycle(all);
+
+            for (c, values) in rest.iter_mut().zip(&others) {
+                let mut written = 0;
+                c.process_slices(meta.band, meta.band + 1, meta.sample_time, time_end, |m, cs| {
+                    let offset = (m.sample_time / step - ci0) as usize;
+                    cs.copy_from_slice(&values[offset..offset + cs.len()]);
+                    written += cs.len();
+                });
+                if written < values.len() {
+                    c.fill(meta.band, meta.band + 1, meta.sample_time, time_end, |m, coef| {
+                        *coef = values[(m.sample_time / step - ci0) as usize];
+                    });
+                }
+            }
+        });
+    }
+
+    /// Apply `effect` to all existing coefficients of all channels, see `SpectralEffect::process_channels`.
+    /// The effect should have been prepared using the same `Gaborator`.
+    /// Fails if the effect does not work on this number of channels, see `SpectralEffect::check_channels`.
+    pub fn apply_effect(&mut self, effect: &mut (impl SpectralEffect + ?Sized)) -> io::Result<()> {
+        self.apply_effect_range(i64::MIN, i64::MAX, effect)
+    }
+
+    /// Apply `effect` to existing coefficients of all channels within the given time range.
+    /// The effect should have been prepared using the same `Gaborator`.
+    /// Fails if the effect does not work on this number of channels, see `SpectralEffect::check_channels`.
+    pub fn apply_effect_range(
+        &mut self,
+        from_sample_time: i64,
+        to_sample_time: i64,
+        effect: &mut (impl SpectralEffect + ?Sized),
+    ) -> io::Result<()> {
+        effect.check_channels(self.n_channels())?;
+        self.process_slices(i32::MIN, i32::MAX, from_sample_time, to_sample_time, |meta, channels| {
+            effect.process_channels(meta, channels)
+        });
+        Ok(())
+    }
+
+    /// Like `Coefs::process`, but the callback receives coefficients of all channels
+    /// for the same band and sample time at once, indexed by channel.
+    /// Same rules as for `process_slices` apply for coefficients missing in some channels.
+    pub fn process(
+        &mut self,
+        from_band: i32,
+        to_band: i32,
+        from_sample_time: i64,
+        to_sample_time: i64,
+        mut callback: impl FnMut(CoefMeta, &mut [Coef]),
+    ) {
+        let mut frame = Vec::with_capacity(self.n_channels());
+        self.process_slices(from_band, to_band, from_sample_time, to_sample_time, |meta, channels| {
+            for i in 0..channels[0].len() {
+                frame.clear();
+                frame.extend(channels.iter().map(|c| c[i]));
+                let sample_time = meta.sample_time + i as i64 * meta.time_step as i64;
+                callback(CoefMeta { band: meta.band, sample_time }, &mut frame);
+                for (c, value) in channels.iter_mut().zip(&frame) {
+                    c[i] = *value;
+                }
+            }
+        })
+    }
+}
+
+impl Gaborator {
+    /// Like `analyze`, but for interleaved multichannel signal: `frames` contains
+    /// `coefs.n_channels()` samples for each sample time, one per channel.
+    pub fn analyze_interleaved(&self, frames: &[f32], signal_begin_sample_number: i64, coefs: &mut MultiCoefs) {
+        let n_channels = coefs.n_channels();
+        assert!(n_channels > 0, "coefs should have at least one channel");
+        assert!(
+            frames.len().is_multiple_of(n_channels),
+            "number of samples should be a multiple of number of channels"
+        );
+        let mut signal = Vec::with_capacity(frames.len() / n_channels);
+        for (channel, c) in coefs.0.iter_mut().enumerate() {
+            signal.clear();
+            signal.extend(frames.iter().skip(channel).step_by(n_channels));
+            self.analyze(&signal, signal_begin_sample_number, c);
+        }
+    }
+
+    /// Like `synthesize`, but for interleaved multichannel signal (see `analyze_interleaved`).
+    pub fn synthesize_interleaved(&self, coefs: &MultiCoefs, signal_begin_sample_number: i64, frames: &mut [f32]) {
+        let n_channels = coefs.n_channels();
+        assert!(n_channels > 0, "coefs should have at least one channel");
+        assert!(
+            frames.len().is_multiple_of(n_channels),
+            "number of samples should be a multiple of number of channels"
+        );
+        let mut signal = vec![0.0; frames.len() / n_channels];
+        for (channel, c) in coefs.0.iter().enumerate() {
+            self.synthesize(c, signal_begin_sample_number, &mut signal);
+            for (out, x) in frames.iter_mut().skip(channel).step_by(n_channels).zip(&signal) {
+                *out = *x;
+            }
+        }
+    }
+}
+
+/// Empty vector reusing the allocation of `v`, for references with another lifetime
+/// (collecting a `Vec`'s own `into_iter` with a same-sized item type happens in place)
+fn recycle<'b>(mut v: Vec<&mut [Coef]>) -> Vec<&'b mut [Coef]> {
+    v.clear();
+    v.into_iter().map(|_| unreachable!()).collect()
+}
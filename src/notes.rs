@@ -0,0 +1,119 @@
+//! Mapping between bands and notes of the equal-tempered scale, with A4 (MIDI note 69) tuned to 440 Hz.
+//!
+//! Bands align with notes when `bands_per_octave` is a multiple of 12 and `ff_ref` is the frequency
+//! of some note, e.g. `GaboratorParams::for_sample_rate(sr, fmin, 440.0, 48)`.
+//! Band methods need the analyzer to know the sample rate (see `Gaborator::with_sample_rate`) and return `None` otherwise.
+
+use crate::Gaborator;
+
+/// Frequency of A4 in Hz
+pub const A4_HZ: f64 = 440.0;
+/// MIDI note number of A4
+pub const A4_MIDI: i32 = 69;
+
+const NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+/// Position on the equal-tempered scale: nearest note and deviation from it
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Note {
+    /// MIDI note number, 60 being the middle C (C4)
+    pub midi: i32,
+    /// Deviation from the note, in cents (hundredths of semitone), from -50 to 50
+    pub cents: f64,
+}
+
+impl Note {
+    /// Nearest note to frequency `hz`, which should be positive
+    pub fn from_hz(hz: f64) -> Self {
+        let m = hz_to_midi(hz);
+        let midi = m.round();
+        Note {
+            midi: midi as i32,
+            cents: (m - midi) * 100.0,
+        }
+    }
+
+    /// Frequency in Hz
+    pub fn hz(&self) -> f64 {
+        midi_to_hz(self.midi as f64 + self.cents / 100.0)
+    }
+
+    /// Name of the note, such as `A4` or `C#3`, ignoring `cents`
+    pub fn name(&self) -> String {
+        note_name(self.midi)
+    }
+}
+
+/// Fractional MIDI note number of frequency `hz`
+pub fn hz_to_midi(hz: f64) -> f64 {
+    A4_MIDI as f64 + 12.0 * (hz / A4_HZ).log2()
+}
+
+/// Frequency in Hz of fractional MIDI note number
+pub fn midi_to_hz(midi: f64) -> f64 {
+    A4_HZ * ((midi - A4_MIDI as f64) / 12.0).exp2()
+}
+
+/// Name of MIDI note, using sharps and scientific octave numbering: 60 is `C4`, 69 is `A4`.
+pub fn note_name(midi: i32) -> String {
+    format!("{}{}", NAMES[midi.rem_euclid(12) as usize], midi.div_euclid(12) - 1)
+}
+
+/// Parse note name such as `A4`, `C#3`, `Bb-1` (sharps `#` and flats `b` are accepted) to MIDI note number.
+pub fn parse_note_name(name: &str) -> Option<i32> {
+    let mut chars = name.chars();
+    let pitch_class = match chars.next()?.to_ascii_uppercase() {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+    let rest = chars.as_str();
+    let (accidental, octave) = if let Some(r) = rest.strip_prefix('#') {
+        (1, r)
+    } else if let Some(r) = rest.strip_prefix('b') {
+        (-1, r)
+    } else {
+        (0, rest)
+    };
+    // `parse` would also accept an explicit plus sign
+    if octave.starts_with('+') {
+        return None;
+    }
+    let octave: i32 = octave.parse().ok()?;
+    Some((octave + 1) * 12 + pitch_class + accidental)
+}
+
+impl Gaborator {
+    /// Note nearest to the center frequency of bandpass band `band`.
+    /// Returns `None` for the lowpass band, or if the analyzer does not know the sample rate.
+    pub fn band_note(&self, band: i32) -> Option<Note> {
+        if band >= self.bandpass_bands_end() {
+            return None;
+        }
+        Some(Note::from_hz(self.band_ff(band) * self.sample_rate?))
+    }
+
+    /// Bandpass band with center frequency closest to the note (see `band_for_hz`)
+    pub fn band_for_note(&self, note: Note) -> Option<i32> {
+        Some(self.band_for_ff(note.hz() / self.sample_rate?))
+    }
+
+    /// Bandpass band closest to MIDI note number `midi`
+    pub fn band_for_midi(&self, midi: i32) -> Option<i32> {
+        Some(self.band_for_ff(midi_to_hz(midi as f64) / self.sample_rate?))
+    }
+
+    /// Whether every note within the bandpass range has a band centered on it:
+    /// `bands_per_octave` is a multiple of 12 and the reference band (`band_ref`) is within 0.1 cent of a note.
+    /// Returns `None` if the analyzer does not know the sample rate.
+    pub fn bands_are_note_aligned(&self) -> Option<bool> {
+        let ref_hz = self.band_ff(self.band_ref()) * self.sample_rate?;
+        Some(self.params().bands_per_octave.is_multiple_of(12) && Note::from_hz(ref_hz).cents.abs() < 0.1)
+    }
+}
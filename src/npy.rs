@@ -0,0 +1,208 @@
+use std::convert::TryFrom;
+use std::io::{self, Write};
+
+use crate::{Coef, Gaborator, Grid};
+
+/// Element type that can be stored in NumPy `.npy` files.
+pub trait NpyElement: Copy {
+    /// NumPy type descriptor, such as `<f4`
+    const DESCR: &'static str;
+
+    /// Append little-endian representation of the value to `out`
+    fn append_le_bytes(&self, out: &mut Vec<u8>);
+}
+
+impl NpyElement for f32 {
+    const DESCR: &'static str = "<f4";
+    fn append_le_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl NpyElement for f64 {
+    const DESCR: &'static str = "<f8";
+    fn append_le_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl NpyElement for i32 {
+    const DESCR: &'static str = "<i4";
+    fn append_le_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl NpyElement for i64 {
+    const DESCR: &'static str = "<i8";
+    fn append_le_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+/// Stored as `complex64`
+impl NpyElement for Coef {
+    const DESCR: &'static str = "<c8";
+    fn append_le_bytes(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.re.to_le_bytes());
+        out.extend_from_slice(&self.im.to_le_bytes());
+    }
+}
+
+/// Serialize C-order array of given shape to `.npy` format version 1.0.
+fn npy_bytes<T: NpyElement>(shape: &[usize], data: &[T]) -> Vec<u8> {
+    debug_assert_eq!(shape.iter().product::<usize>(), data.len());
+    let shape = match shape {
+        [n] => format!("({},)", n),
+        _ => format!("({})", shape.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+    let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", T::DESCR, shape);
+    // Magic, version and header length take 10 bytes; data should start at a multiple of 64.
+    while (10 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+
+    let mut out = Vec::with_capacity(10 + header.len() + std::mem::size_of_val(data));
+    out.extend_from_slice(b"\x93NUMPY\x01\x00");
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    for x in data {
+        x.append_le_bytes(&mut out);
+    }
+    out
+}
+
+/// Write one-dimensional array to `.npy` file, loadable by `numpy.load`.
+pub fn write_npy<T: NpyElement>(mut w: impl Write, data: &[T]) -> io::Result<()> {
+    w.write_all(&npy_bytes(&[data.len()], data))?;
+    w.flush()
+}
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+static CRC32_TABLE: [u32; 256] = crc32_table();
+
+fn crc32(data: &[u8]) -> u32 {
+    !data
+        .iter()
+        .fold(!0u32, |c, b| CRC32_TABLE[((c ^ *b as u32) & 0xFF) as usize] ^ (c >> 8))
+}
+
+/// Write uncompressed zip archive (which is what `.npz` is) with given members.
+fn write_zip(mut w: impl Write, members: &[(&str, Vec<u8>)]) -> io::Result<()> {
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "data is too large for .npz without Zip64");
+    // DOS date of 1980-01-01, time 00:00
+    const DATE: u16 = (1 << 5) | 1;
+
+    let mut offset = 0u32;
+    let mut central = Vec::new();
+    for (name, data) in members {
+        let crc = crc32(data);
+        let size = u32::try_from(data.len()).map_err(|_| too_large())?;
+
+        let mut local = Vec::new();
+        local.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        local.extend_from_slice(&20u16.to_le_bytes()); // version needed to extract
+        local.extend_from_slice(&0u16.to_le_bytes()); // flags
+        local.extend_from_slice(&0u16.to_le_bytes()); // method: stored
+        local.extend_from_slice(&0u16.to_le_bytes()); // time
+        local.extend_from_slice(&DATE.to_le_bytes());
+        local.extend_from_slice(&crc.to_le_bytes());
+        local.extend_from_slice(&size.to_le_bytes()); // compressed size
+        local.extend_from_slice(&size.to_le_bytes()); // uncompressed size
+        local.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        local.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+        local.extend_from_slice(name.as_bytes());
+
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        central.extend_from_slice(&local[4..30]);
+        central.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        central.extend_from_slice(&0u16.to_le_bytes()); // disk number
+        central.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+        central.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+
+        w.write_all(&local)?;
+        w.write_all(data)?;
+        offset = offset
+            .checked_add(local.len() as u32)
+            .and_then(|o| o.checked_add(size))
+            .ok_or_else(too_large)?;
+    }
+
+    let n = members.len() as u16;
+    w.write_all(&central)?;
+    w.write_all(&0x0605_4b50u32.to_le_bytes())?;
+    w.write_all(&0u16.to_le_bytes())?; // this disk
+    w.write_all(&0u16.to_le_bytes())?; // disk with central directory
+    w.write_all(&n.to_le_bytes())?;
+    w.write_all(&n.to_le_bytes())?;
+    w.write_all(&(central.len() as u32).to_le_bytes())?;
+    w.write_all(&offset.to_le_bytes())?;
+    w.write_all(&0u16.to_le_bytes())?; // comment length
+    w.flush()
+}
+
+impl<T> Grid<T> {
+    /// Band numbers of the rows
+    pub fn band_axis(&self) -> Vec<i32> {
+        (self.band_begin..self.band_end()).collect()
+    }
+
+    /// Center frequencies of the rows' bands, as fractions of the sample rate
+    pub fn band_ff_axis(&self, g: &Gaborator) -> Vec<f64> {
+        (self.band_begin..self.band_end()).map(|b| g.band_ff(b)).collect()
+    }
+
+    /// Sample times of the columns
+    pub fn time_axis(&self) -> Vec<i64> {
+        (0..self.n_frames).map(|f| self.frame_time(f)).collect()
+    }
+}
+
+impl<T: NpyElement> Grid<T> {
+    /// Write values to `.npy` file as `n_bands × n_frames` array.
+    ///
+    /// `Grid<Coef>` is stored as `complex64`, `Grid<f32>` as `float32`.
+    pub fn write_npy(&self, mut w: impl Write) -> io::Result<()> {
+        w.write_all(&npy_bytes(&[self.n_bands, self.n_frames], &self.data))?;
+        w.flush()
+    }
+
+    /// Write `.npz` archive with values together with their axes, loadable by `numpy.load`:
+    ///
+    /// * `values` - `n_bands × n_frames` array, as in `write_npy`
+    /// * `band` - band numbers of the rows (`int32`)
+    /// * `band_ff` - center frequencies of the rows' bands, as fractions of the sample rate (`float64`)
+    /// * `time` - sample times of the columns (`int64`)
+    ///
+    /// The archive is not compressed.
+    pub fn write_npz(&self, g: &Gaborator, w: impl Write) -> io::Result<()> {
+        write_zip(
+            w,
+            &[
+                ("values.npy", npy_bytes(&[self.n_bands, self.n_frames], &self.data)),
+                ("band.npy", npy_bytes(&[self.n_bands], &self.band_axis())),
+                ("band_ff.npy", npy_bytes(&[self.n_bands], &self.band_ff_axis(g))),
+                ("time.npy", npy_bytes(&[self.n_frames], &self.time_axis())),
+            ],
+        )
+    }
+}
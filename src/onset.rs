@@ -0,0 +1,198 @@
+//! Onset detection: a novelty curve on a uniform hop, measuring how much the spectrum changes from frame to frame,
+//! and adaptive peak picking on it.
+//!
+//! Bands whose time step is longer than the hop are compared with frames one time step back instead of
+//! the previous frame, so slowly sampled bass bands contribute as much as the treble ones.
+//! Each band's contribution can be weighted by its center frequency.
+//!
+//! Needs the analyzer to know the sample rate (see `Gaborator::with_sample_rate`).
+
+use std::io;
+use std::ops::Range;
+
+use crate::{Coef, Coefs, Gaborator, Interp};
+
+/// How spectral change between frames is measured
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NoveltyMethod {
+    /// Increase of (compressed) band magnitudes, ignoring decreases
+    SpectralFlux,
+    /// Distance of coefficients from values predicted by continuing their magnitude and phase advance,
+    /// counted only where magnitude does not decrease. Also reacts to soft onsets with a phase change,
+    /// but also to beating of close partials within a band.
+    ComplexDomain,
+}
+
+/// Settings of onset detection
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OnsetParams {
+    /// Distance between frames of the novelty curve, in samples
+    pub hop: u32,
+    /// Novelty measure
+    pub method: NoveltyMethod,
+    /// Only bands with center frequencies within this range (in Hz) are used
+    pub hz_range: Range<f64>,
+    /// Logarithmic compression of magnitudes for spectral flux, `ln(1 + compression * magnitude)`; 0 means none
+    pub compression: f32,
+    /// Number of frames on each side whose median novelty is the adaptive threshold
+    pub median_window: usize,
+    /// Added to the adaptive threshold, as fraction of the largest novelty
+    pub threshold_offset: f32,
+    /// Onset is a maximum of novelty within this number of frames on each side, which is also the minimum
+    /// distance between onsets
+    pub peak_window: usize,
+}
+
+impl Default for OnsetParams {
+    fn default() -> Self {
+        OnsetParams {
+            hop: 256,
+            method: NoveltyMethod::SpectralFlux,
+            hz_range: 30.0..16000.0,
+            compression: 100.0,
+            median_window: 8,
+            threshold_offset: 0.05,
+            peak_window: 3,
+        }
+    }
+}
+
+/// Novelty curve: one value every `hop` samples starting from `time_begin`
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Novelty {
+    /// Sample time of the first value
+    pub time_begin: i64,
+    /// Distance between values, in samples
+    pub hop: u32,
+    /// Novelty of each frame
+    pub values: Vec<f32>,
+}
+
+impl Novelty {
+    /// Sample time of value number `frame`
+    pub fn frame_time(&self, frame: usize) -> i64 {
+        self.time_begin + frame as i64 * self.hop as i64
+    }
+}
+
+/// Novelty curve for frames every `params.hop` samples, starting from `time_range.start` and not reaching `time_range.end`.
+///
+/// `band_weight` gives weight of a band by its center frequency in Hz; the result is normalized by the sum of weights.
+///
+/// Returns `InvalidInput` error if the analyzer does not know the sample rate.
+pub fn novelty(
+    g: &Gaborator,
+    coefs: &mut Coefs,
+    time_range: Range<i64>,
+    params: &OnsetParams,
+    mut band_weight: impl FnMut(f64) -> f32,
+) -> io::Result<Novelty> {
+    let hop = params.hop;
+    let sample_rate = g.require_sample_rate()?;
+    let bands = g.bands_for_ff_range(params.hz_range.start / sample_rate..params.hz_range.end / sample_rate);
+    // Frames to compare with, for each band
+    let lag = |band: i32| ((g.band_time_step(band) as f64 / hop as f64).round() as usize).max(1);
+
+    let mut total_weight = 0.0;
+    let values = match params.method {
+        NoveltyMethod::SpectralFlux => {
+            let energy = coefs.to_energy_grid(g, hop, time_range.clone(), bands);
+            let mut values = vec![0.0; energy.n_frames];
+            let mut row = Vec::with_capacity(energy.n_frames);
+            for band in energy.band_begin..energy.band_end() {
+                let w = band_weight(g.band_ff(band) * sample_rate);
+                total_weight += w;
+                if w == 0.0 {
+                    continue;
+                }
+                row.clear();
+                row.extend(energy.row(band).iter().map(|e| {
+                    if params.compression > 0.0 {
+                        (params.compression * e.sqrt()).ln_1p()
+                    } else {
+                        e.sqrt()
+                    }
+                }));
+                let lag = lag(band);
+                for n in lag..row.len() {
+                    let rise = row[n] - row[n - lag];
+                    if rise > 0.0 {
+                        values[n] += w * rise / lag as f32;
+                    }
+                }
+            }
+            values
+        }
+        NoveltyMethod::ComplexDomain => {
+            let grid = coefs.to_grid(g, hop, time_range.clone(), bands, Interp::Linear);
+            let mut values = vec![0.0; grid.n_frames];
+            for band in grid.band_begin..grid.band_end() {
+                let w = band_weight(g.band_ff(band) * sample_rate);
+                total_weight += w;
+                if w == 0.0 {
+                    continue;
+                }
+                let row = grid.row(band);
+                let lag = lag(band);
+                for n in 2 * lag..row.len() {
+                    let (x, x1, x2) = (row[n], row[n - lag], row[n - 2 * lag]);
+                    if x.norm() < x1.norm() {
+                        continue;
+                    }
+                    let predicted = Coef::from_polar(x1.norm(), 2.0 * x1.arg() - x2.arg());
+                    values[n] += w * (x - predicted).norm() / lag as f32;
+                }
+            }
+            values
+        }
+    };
+
+    let mut novelty = Novelty {
+        time_begin: time_range.start,
+        hop,
+        values,
+    };
+    if total_weight > 0.0 {
+        for v in &mut novelty.values {
+            *v /= total_weight;
+        }
+    }
+    Ok(novelty)
+}
+
+/// Sample times of novelty peaks rising above the adaptive threshold: median of the surrounding novelty
+/// plus `params.threshold_offset`.
+pub fn pick_onsets(novelty: &Novelty, params: &OnsetParams) -> Vec<i64> {
+    let values = &novelty.values;
+    let max = values.iter().cloned().fold(0.0, f32::max);
+    if max <= 0.0 {
+        return Vec::new();
+    }
+    let window = |n: usize, half: usize| n.saturating_sub(half)..(n + half + 1).min(values.len());
+
+    let mut sorted = Vec::with_capacity(2 * params.median_window + 1);
+    let mut onsets = Vec::new();
+    for (n, v) in values.iter().enumerate() {
+        // Strictly greater than earlier values, so a plateau gives one onset
+        let peak = window(n, params.peak_window);
+        if values[peak.start..n].iter().any(|x| x >= v) || values[n + 1..peak.end].iter().any(|x| x > v) {
+            continue;
+        }
+        sorted.clear();
+        sorted.extend_from_slice(&values[window(n, params.median_window)]);
+        let mid = sorted.len() / 2;
+        let median = *sorted.select_nth_unstable_by(mid, |a, b| a.total_cmp(b)).1;
+        if *v > median + params.threshold_offset * max {
+            onsets.push(novelty.frame_time(n));
+        }
+    }
+    onsets
+}
+
+/// Sample times of onsets within `time_range`, with all bands weighted equally (see `novelty` and `pick_onsets`).
+pub fn detect_onsets(g: &Gaborator, coefs: &mut Coefs, time_range: Range<i64>, params: &OnsetParams) -> io::Result<Vec<i64>> {
+    Ok(pick_onsets(&novelty(g, coefs, time_range, params, |_| 1.0)?, params))
+}
@@ -0,0 +1,277 @@
+//! Monophonic pitch (fundamental frequency) tracking.
+//!
+//! Each frame gets a harmonic-sum salience for every bandpass band within the search range taken as a candidate
+//! fundamental: weighted sum of magnitudes at its first harmonics, interpolated between adjacent bands.
+//! The strongest candidate is refined by parabolic interpolation of the salience between adjacent bands,
+//! giving resolution finer than the band spacing.
+//!
+//! Octave errors are corrected twice: a candidate an octave lower with nearly the same salience is preferred
+//! (a missing or weak fundamental), and voiced frames jumping by whole octaves from the median of their neighbours
+//! are moved back if the salience supports it.
+//!
+//! Needs the analyzer to know the sample rate (see `Gaborator::with_sample_rate`).
+
+use std::io;
+use std::ops::Range;
+
+use crate::notes::Note;
+use crate::{Coefs, Gaborator, Grid};
+
+/// Minimum salience, relative to the strongest candidate of the frame, for the neighbourhood-based octave correction
+const OCTAVE_JUMP_SALIENCE: f32 = 0.5;
+/// Maximum deviation from a whole number of octaves, in octaves, for a jump to be considered an octave error
+const OCTAVE_JUMP_TOLERANCE: f64 = 0.15;
+
+/// Settings of `track_pitch`
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PitchParams {
+    /// Distance between frames, in samples
+    pub hop: u32,
+    /// Range of fundamental frequencies searched, in Hz
+    pub hz_range: Range<f64>,
+    /// Number of harmonics, including the fundamental, summed for salience
+    pub n_harmonics: usize,
+    /// Weight of each harmonic relative to the previous one
+    pub harmonic_weight: f32,
+    /// A fundamental an octave lower is preferred if its salience is at least this fraction of the strongest one
+    pub octave_tolerance: f32,
+    /// Number of frames on each side used to correct octave jumps; 0 disables the correction
+    pub octave_window: usize,
+    /// Frames with confidence at least this are voiced
+    pub voicing_threshold: f32,
+    /// Frames with energy below this, in decibels relative to the loudest frame (so normally negative), get zero confidence
+    pub silence_db: f32,
+}
+
+impl Default for PitchParams {
+    fn default() -> Self {
+        PitchParams {
+            hop: 256,
+            hz_range: 60.0..1000.0,
+            n_harmonics: 6,
+            harmonic_weight: 0.8,
+            octave_tolerance: 0.9,
+            octave_window: 5,
+            voicing_threshold: 0.75,
+            silence_db: -60.0,
+        }
+    }
+}
+
+/// Pitch estimate of one frame
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PitchFrame {
+    /// Sample time of the frame's center
+    pub sample_time: i64,
+    /// Estimated fundamental frequency in Hz, also given for unvoiced frames (0 if there is no candidate)
+    pub hz: f64,
+    /// Voicing confidence from 0 to 1: how much the salience peak stands out of the median salience
+    pub confidence: f32,
+    /// Whether `confidence` reaches `PitchParams::voicing_threshold`
+    pub voiced: bool,
+}
+
+impl PitchFrame {
+    /// Nearest note to the estimated frequency, `None` for unvoiced frames
+    pub fn note(&self) -> Option<Note> {
+        if self.voiced {
+            Some(Note::from_hz(self.hz))
+        } else {
+            None
+        }
+    }
+}
+
+/// Harmonic-sum salience of candidate fundamentals
+struct Salience {
+    /// Magnitudes of bands summed
+    magnitudes: Grid<f32>,
+    sample_rate: f64,
+    /// Band of the first candidate; candidates are consecutive bands
+    first_candidate: i32,
+    n_candidates: usize,
+    /// For each candidate, (offset in `magnitudes.data` of a row, weight)
+    taps: Vec<Vec<(usize, f32)>>,
+}
+
+impl Salience {
+    fn new(g: &Gaborator, sample_rate: f64, coefs: &mut Coefs, time_range: Range<i64>, params: &PitchParams) -> Self {
+        let candidates = g.bands_for_ff_range(params.hz_range.start / sample_rate..params.hz_range.end / sample_rate);
+        let candidates = candidates.start..candidates.end.min(g.bandpass_bands_end());
+        // From the highest harmonic of the highest candidate to a band below the lowest candidate
+        let bands = if candidates.is_empty() {
+            0..0
+        } else {
+            let top_ff = g.band_ff(candidates.start) * params.n_harmonics.max(1) as f64;
+            g.band_for_ff(top_ff) - 1..candidates.end + 1
+        };
+        let bands = bands.start..bands.end.min(g.bandpass_bands_end());
+        let magnitudes = coefs.to_energy_grid(g, params.hop, time_range, bands).map(|e| e.sqrt());
+
+        let taps = candidates
+            .clone()
+            .map(|band| {
+                let mut taps = Vec::new();
+                let mut weight = 1.0;
+                for h in 1..=params.n_harmonics {
+                    // Linear interpolation between the two bands around the harmonic
+                    let position = g.band_position_for_ff(g.band_ff(band) * h as f64);
+                    let lower = position.floor();
+                    let frac = (position - lower) as f32;
+                    for (b, w) in [(lower as i32, 1.0 - frac), (lower as i32 + 1, frac)] {
+                        if b >= magnitudes.band_begin && b < magnitudes.band_end() && w > 0.0 {
+                            let offset = (b - magnitudes.band_begin) as usize * magnitudes.n_frames;
+                            taps.push((offset, weight * w));
+                        }
+                    }
+                    weight *= params.harmonic_weight;
+                }
+                taps
+            })
+            .collect();
+        Salience {
+            magnitudes,
+            sample_rate,
+            first_candidate: candidates.start,
+            n_candidates: candidates.len(),
+            taps,
+        }
+    }
+
+    /// Salience of all candidates at frame number `frame`
+    fn compute(&self, frame: usize, out: &mut Vec<f32>) {
+        let data = &self.magnitudes.data;
+        out.clear();
+        out.extend(self.taps.iter().map(|taps| taps.iter().map(|(offset, w)| data[offset + frame] * w).sum::<f32>()));
+    }
+
+    /// Energy of all bands summed at frame number `frame`
+    fn energy(&self, frame: usize) -> f32 {
+        let n_frames = self.magnitudes.n_frames;
+        self.magnitudes.data[frame..].iter().step_by(n_frames).map(|m| m * m).sum()
+    }
+
+    /// Frequency of fractional candidate index
+    fn hz(&self, g: &Gaborator, index: f64) -> f64 {
+        g.ff_for_band_position(self.first_candidate as f64 + index) * self.sample_rate
+    }
+
+    /// Fractional candidate index of frequency `hz`
+    fn index(&self, g: &Gaborator, hz: f64) -> f64 {
+        g.band_position_for_ff(hz / self.sample_rate) - self.first_candidate as f64
+    }
+}
+
+/// Index of the largest value within `range` of `values`
+fn argmax(values: &[f32], range: Range<usize>) -> Option<usize> {
+    let range = range.start..range.end.min(values.len());
+    range.max_by(|a, b| values[*a].total_cmp(&values[*b]))
+}
+
+/// Peak position refined by fitting a parabola through the peak and its neighbours
+pub(crate) fn parabolic(values: &[f32], k: usize) -> f64 {
+    if k == 0 || k + 1 >= values.len() {
+        return k as f64;
+    }
+    let (a, b, c) = (values[k - 1], values[k], values[k + 1]);
+    let curvature = a - 2.0 * b + c;
+    if curvature >= 0.0 {
+        return k as f64;
+    }
+    k as f64 + (0.5 * (a - c) / curvature).clamp(-0.5, 0.5) as f64
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    let mid = values.len() / 2;
+    *values.select_nth_unstable_by(mid, |a, b| a.total_cmp(b)).1
+}
+
+/// Track the fundamental frequency of a monophonic signal: one estimate for frames every `params.hop` samples
+/// (see `Coefs::to_energy_grid`), starting from `time_range.start` and not reaching `time_range.end`.
+///
+/// Returns `InvalidInput` error if the analyzer does not know the sample rate.
+pub fn track_pitch(g: &Gaborator, coefs: &mut Coefs, time_range: Range<i64>, params: &PitchParams) -> io::Result<Vec<PitchFrame>> {
+    let salience = Salience::new(g, g.require_sample_rate()?, coefs, time_range, params);
+    let n_frames = salience.magnitudes.n_frames;
+    let bpo = g.params().bands_per_octave as usize;
+
+    let max_energy = (0..n_frames).map(|f| salience.energy(f)).fold(0.0, f32::max);
+    let silence = max_energy * 10f32.powf(params.silence_db / 10.0);
+
+    let mut values = Vec::with_capacity(salience.n_candidates);
+    let mut sorted = Vec::with_capacity(salience.n_candidates);
+    let mut frames: Vec<PitchFrame> = (0..n_frames)
+        .map(|f| {
+            let mut frame = PitchFrame {
+                sample_time: salience.magnitudes.frame_time(f),
+                hz: 0.0,
+                confidence: 0.0,
+                voiced: false,
+            };
+            salience.compute(f, &mut values);
+            let mut k = match argmax(&values, 0..values.len()) {
+                Some(k) => k,
+                None => return frame,
+            };
+            let peak = values[k];
+
+            // Prefer lower octaves with nearly the same salience
+            while let Some(j) = argmax(&values, k + bpo - 1..k + bpo + 2) {
+                if values[j] < params.octave_tolerance * peak {
+                    break;
+                }
+                k = j;
+            }
+            frame.hz = salience.hz(g, parabolic(&values, k));
+
+            if peak > 0.0 && salience.energy(f) > silence {
+                sorted.clear();
+                sorted.extend(values.iter().map(|v| *v as f64));
+                frame.confidence = (1.0 - median(&mut sorted) / peak as f64).max(0.0) as f32;
+            }
+            frame.voiced = frame.confidence >= params.voicing_threshold;
+            frame
+        })
+        .collect();
+
+    if params.octave_window > 0 {
+        let mut neighbours = Vec::with_capacity(2 * params.octave_window + 1);
+        let corrected: Vec<Option<f64>> = (0..n_frames)
+            .map(|f| {
+                if !frames[f].voiced {
+                    return None;
+                }
+                let window = f.saturating_sub(params.octave_window)..(f + params.octave_window + 1).min(n_frames);
+                neighbours.clear();
+                neighbours.extend(frames[window].iter().filter(|x| x.voiced).map(|x| x.hz));
+                let octaves = (frames[f].hz / median(&mut neighbours)).log2();
+                let whole = octaves.round();
+                if whole == 0.0 || (octaves - whole).abs() > OCTAVE_JUMP_TOLERANCE {
+                    return None;
+                }
+
+                // Strongest candidate near the frequency moved by whole octaves
+                let target = salience.index(g, frames[f].hz * (-whole).exp2()).round();
+                if target < 0.0 || target >= salience.n_candidates as f64 {
+                    return None;
+                }
+                salience.compute(f, &mut values);
+                let target = target as usize;
+                let j = argmax(&values, target.saturating_sub(2)..target + 3)?;
+                let peak = values.iter().cloned().fold(0.0, f32::max);
+                if values[j] < OCTAVE_JUMP_SALIENCE * peak {
+                    return None;
+                }
+                Some(salience.hz(g, parabolic(&values, j)))
+            })
+            .collect();
+        for (frame, hz) in frames.iter_mut().zip(corrected) {
+            if let Some(hz) = hz {
+                frame.hz = hz;
+            }
+        }
+    }
+    Ok(frames)
+}
@@ -0,0 +1,291 @@
+//! Pitch shifting: transposing a signal without changing its duration, by moving coefficients between bands.
+//!
+//! Bands are spaced logarithmically, so moving every coefficient by `k` bands transposes by `k / bands_per_octave`
+//! octaves. Shifts by fractions of a band interpolate energy between the two nearest source bands.
+//! Bands differ in time step, so each output coefficient samples its source bands at its own time, interpolating
+//! magnitudes linearly. Phase advances at the transposed instantaneous frequency: measured from phase differences
+//! of adjacent coefficients of the nearest source band (as in `stretch` module), multiplied by the pitch ratio
+//! and demodulated by the output band's center frequency.
+//!
+//! Phase starts over where a band becomes audible and at transients (detected as in `stretch` module)
+//! where the energy of its source band rises, in a way all bands agree on for both stationary partials and impulses.
+//! Optionally, the spectral envelope is kept in place, so formants of voices and instrument bodies do not move
+//! with the pitch. It is estimated on frames of band energies (see `Coefs::to_energy_grid`) by interpolating
+//! logarithmic magnitude linearly between spectral peaks, so that it passes through partials however far apart.
+//!
+//! Content shifted outside of the bandpass bands is dropped, and the lowpass band is left empty.
+
+use std::f64::consts::TAU;
+
+use crate::stretch::{band_rises, princarg, read_bands, silence_floor, transient_times, BandInput};
+use crate::{Coef, Coefs, Gaborator, Grid};
+
+/// Distance between frames of the spectral envelope, in samples
+const ENVELOPE_HOP: u32 = 256;
+/// Spectral peaks quieter than this, in decibels relative to the loudest band of the frame, are left out
+/// of the envelope, so that it does not dip into the leakage between partials far apart
+const ENVELOPE_FLOOR_DB: f32 = -60.0;
+
+/// Settings of `pitch_shift_with`
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShiftParams {
+    /// Keep the spectral envelope in place, so that formants do not move with the pitch
+    pub preserve_formants: bool,
+    /// Rise of a band's energy regarded as a transient, see `StretchParams::transient_db`
+    pub transient_db: f32,
+    /// Number of coefficients on each side of a transient regarded as part of it, see `StretchParams::transient_coefs`
+    pub transient_coefs: u32,
+    /// Minimum number of bands seeing a transient, see `StretchParams::transient_bands`
+    pub transient_bands: u32,
+}
+
+impl Default for ShiftParams {
+    fn default() -> Self {
+        ShiftParams {
+            preserve_formants: false,
+            transient_db: 6.0,
+            transient_coefs: 8,
+            transient_bands: 8,
+        }
+    }
+}
+
+/// Existing bandpass bands of the input
+struct Sources<'a> {
+    by_band: Vec<Option<&'a BandInput>>,
+    /// Spectral envelope, if formants are preserved
+    envelope: Option<Grid<f32>>,
+}
+
+impl<'a> Sources<'a> {
+    fn get(&self, band: i64) -> Option<&'a BandInput> {
+        if band < 0 {
+            return None;
+        }
+        self.by_band.get(band as usize).cloned().flatten()
+    }
+
+    /// Magnitude of `band` at sample time `t`, interpolated linearly between its coefficients
+    fn magnitude(&self, band: i64, t: f64) -> f64 {
+        let input = match self.get(band) {
+            Some(input) => input,
+            None => return 0.0,
+        };
+        let x = t / input.time_step as f64 - input.ci0 as f64;
+        let lower = x.floor();
+        let frac = x - lower;
+        let at = |i: f64| {
+            if i >= 0.0 && i < input.magnitudes.len() as f64 {
+                input.magnitudes[i as usize]
+            } else {
+                0.0
+            }
+        };
+        at(lower) * (1.0 - frac) + at(lower + 1.0) * frac
+    }
+
+    /// Magnitude of fractional band `position` at sample time `t`, from energy interpolated linearly between the nearest bands
+    fn magnitude_between(&self, position: f64, t: f64) -> f64 {
+        let lower = position.floor();
+        let frac = position - lower;
+        (self.magnitude(lower as i64, t).powi(2) * (1.0 - frac) + self.magnitude(lower as i64 + 1, t).powi(2) * frac).sqrt()
+    }
+
+    /// Spectral envelope at fractional band `position` and sample time `t`, interpolated linearly
+    /// between frames and bands
+    fn envelope(&self, position: f64, t: f64) -> f64 {
+        let envelope = match &self.envelope {
+            Some(envelope) if envelope.n_frames > 0 && envelope.n_bands > 0 => envelope,
+            _ => return 0.0,
+        };
+        let lerp = |x: f64, n: usize, value: &dyn Fn(usize) -> f64| {
+            let x = x.max(0.0).min((n - 1) as f64);
+            let lower = x.floor();
+            let upper = (lower as usize + 1).min(n - 1);
+            value(lower as usize) * (1.0 - (x - lower)) + value(upper) * (x - lower)
+        };
+        let frame = (t - envelope.time_begin as f64) / envelope.hop as f64;
+        lerp(position - envelope.band_begin as f64, envelope.n_bands, &|b| {
+            lerp(frame, envelope.n_frames, &|f| envelope.data[b * envelope.n_frames + f] as f64)
+        })
+    }
+}
+
+/// Spectral envelope of each frame of `energy`: magnitudes of peaks (maxima within `half_window` bands on each side,
+/// down to `ENVELOPE_FLOOR_DB`), with logarithmic magnitude interpolated linearly between them and held beyond the outermost ones
+fn spectral_envelope(energy: &Grid<f32>, half_window: usize) -> Grid<f32> {
+    let mut envelope = energy.map(|e| e.sqrt());
+    let (n_bands, n_frames) = (envelope.n_bands, envelope.n_frames);
+    let mut column = vec![0.0; n_bands];
+    let mut peaks = Vec::new();
+    for f in 0..n_frames {
+        for (b, m) in column.iter_mut().enumerate() {
+            *m = envelope.data[b * n_frames + f];
+        }
+        let floor = column.iter().cloned().fold(0.0, f32::max) * 10f32.powf(ENVELOPE_FLOOR_DB / 20.0);
+        peaks.clear();
+        peaks.extend((0..n_bands).filter(|b| {
+            let m = column[*b];
+            m > 0.0 && m >= floor && column[b.saturating_sub(half_window)..(b + half_window + 1).min(n_bands)].iter().all(|x| *x <= m)
+        }));
+        for (b, m) in column.iter().enumerate() {
+            let i = peaks.partition_point(|p| *p < b);
+            let value = match (i.checked_sub(1).map(|i| peaks[i]), peaks.get(i).cloned()) {
+                (_, Some(next)) if next == b => *m,
+                (Some(prev), Some(next)) => {
+                    let frac = (b - prev) as f32 / (next - prev) as f32;
+                    (column[prev].ln() * (1.0 - frac) + column[next].ln() * frac).exp()
+                }
+                (Some(p), None) | (None, Some(p)) => column[p],
+                (None, None) => 0.0,
+            };
+            envelope.data[b * n_frames + f] = value;
+        }
+    }
+    envelope
+}
+
+/// Output coefficients of `band`, taken from fractional band `position` and transposed by `ratio`.
+/// `transients` are sample times of transients in increasing order.
+/// Returns index of the first output coefficient and the output, or `None` if there is no source band.
+fn shift_band(
+    g: &Gaborator,
+    sources: &Sources,
+    band: i32,
+    position: f64,
+    ratio: f64,
+    transients: &[f64],
+    params: &ShiftParams,
+) -> Option<(i64, Vec<Coef>)> {
+    // Phase comes from the nearest source band
+    let nearest = sources.get(position.round() as i64)?;
+    let step = g.band_time_step(band);
+    let source_step = nearest.time_step as f64;
+    let n = nearest.coefs.len();
+    let phases: Vec<f64> = nearest.coefs.iter().map(|c| c.arg() as f64).collect();
+    let advances: Vec<f64> = phases.windows(2).map(|p| princarg(p[1] - p[0])).collect();
+    let floor = silence_floor(&nearest.magnitudes);
+    let source_ff = g.band_ff(nearest.band);
+    let ff = g.band_ff(band);
+    // Fractional index of the source coefficient at sample time `t`
+    let index = |t: f64| t / source_step - nearest.ci0 as f64;
+    // Demodulated phase rates per sample of the source and the output at sample time `t`
+    let rates = |t: f64| {
+        let j = index(t).floor().max(0.0) as usize;
+        let rate = advances.get(j.min(n.saturating_sub(2))).cloned().unwrap_or(0.0) / source_step;
+        (rate, ratio * (TAU * source_ff + rate) - TAU * ff)
+    };
+    let region_half = params.transient_coefs.max(1) as f64 * source_step;
+    // Only transients the source band sees its energy rise at, as in `stretch` module
+    let transients: Vec<f64> = band_rises(nearest, transients, params.transient_db, params.transient_coefs)
+        .into_iter()
+        .map(|p| (nearest.ci0 as f64 + p) * source_step)
+        .collect();
+
+    let k0 = (nearest.ci0 as f64 * source_step / step as f64).floor() as i64;
+    let k1 = (((nearest.ci0 + n as i64) as f64 * source_step) / step as f64).ceil() as i64;
+    let mut output = Vec::with_capacity((k1 - k0).max(0) as usize);
+    let mut phase = 0.0;
+    let mut previous: Option<Option<usize>> = None;
+    let mut next_transient = 0;
+    for k in k0..k1 {
+        let t = (k * step) as f64;
+        let mut magnitude = sources.magnitude_between(position, t);
+        if params.preserve_formants {
+            let source_envelope = sources.envelope(position, t);
+            magnitude *= if source_envelope > 0.0 { sources.envelope(band as f64, t) / source_envelope } else { 0.0 };
+        }
+
+        while next_transient < transients.len() && transients[next_transient] <= t {
+            next_transient += 1;
+        }
+        // Transient whose region the coefficient is in, and the latest one not after it
+        let region = [next_transient.checked_sub(1), Some(next_transient)]
+            .iter()
+            .flatten()
+            .cloned()
+            .find(|i| transients.get(*i).is_some_and(|tr| (tr - t).abs() <= region_half));
+        let reference = region.or_else(|| next_transient.checked_sub(1)).map_or(0.0, |i| transients[i]);
+
+        phase = match previous {
+            Some(last_region) if region.is_none() || region == last_region => {
+                (phase + rates(t - step as f64 / 2.0).1 * step as f64).rem_euclid(TAU)
+            }
+            // Start over from the source phase, as if the signal was an impulse at the reference time
+            // transposed at the measured frequency since. All bands agree on this for stationary partials
+            // (up to a phase common to all bands) and impulses at the reference time.
+            _ => {
+                let x = index(t);
+                let i = x.floor().max(0.0).min((n - 1) as f64) as usize;
+                let (rate, rate_out) = rates(t);
+                let source_phase = phases[i] + rate * (x - i as f64) * source_step;
+                let demodulation = TAU * ((source_ff - ff) * reference).fract();
+                (source_phase + demodulation + (rate_out - rate) * (t - reference)).rem_euclid(TAU)
+            }
+        };
+        // Phase accumulated through silence means nothing, so it starts over when the band becomes audible
+        previous = if sources.magnitude(nearest.band as i64, t) > floor { Some(region) } else { None };
+        output.push(Coef::from_polar(magnitude as f32, phase as f32));
+    }
+    Some((k0, output))
+}
+
+/// Transpose existing coefficients by `semitones` (positive is up) without changing duration.
+/// Uses default `ShiftParams`.
+pub fn pitch_shift(g: &Gaborator, coefs: &mut Coefs, semitones: f64) -> Coefs {
+    pitch_shift_with(g, coefs, semitones, &ShiftParams::default())
+}
+
+/// Like `pitch_shift`, with given settings
+pub fn pitch_shift_with(g: &Gaborator, coefs: &mut Coefs, semitones: f64, params: &ShiftParams) -> Coefs {
+    assert!(semitones.is_finite(), "semitones should be finite");
+    let bands_per_octave = g.params().bands_per_octave as f64;
+    let ratio = 2f64.powf(semitones / 12.0);
+    // Bands go from high to low frequencies, so higher output comes from bands with higher numbers
+    let shift = semitones / 12.0 * bands_per_octave;
+
+    let inputs = read_bands(g, coefs);
+    let transients = transient_times(&inputs, params.transient_db, params.transient_coefs, params.transient_bands);
+    let bandpass = g.bandpass_bands_begin()..g.bandpass_bands_end();
+    let envelope = if params.preserve_formants {
+        let time_begin = inputs.iter().map(|input| input.ci0 * input.time_step).min().unwrap_or(0);
+        let time_end = inputs.iter().map(|input| (input.ci0 + input.coefs.len() as i64) * input.time_step).max().unwrap_or(0);
+        let energy = coefs.to_energy_grid(g, ENVELOPE_HOP, time_begin..time_end, bandpass.clone());
+        // Peaks at least a semitone apart
+        Some(spectral_envelope(&energy, (bands_per_octave / 12.0).round().max(1.0) as usize))
+    } else {
+        None
+    };
+    let mut sources = Sources {
+        by_band: vec![None; g.band_lowpass() as usize + 1],
+        envelope,
+    };
+    for input in inputs.iter().filter(|input| bandpass.contains(&input.band)) {
+        sources.by_band[input.band as usize] = Some(input);
+    }
+
+    let mut output = Coefs::new(g);
+    for band in bandpass {
+        let (k0, values) = match shift_band(g, &sources, band, band as f64 + shift, ratio, &transients, params) {
+            Some(shifted) => shifted,
+            None => continue,
+        };
+        let step = g.band_time_step(band);
+        let k1 = k0 + values.len() as i64;
+        output.fill(band, band + 1, k0 * step, k1 * step, |meta, c| {
+            *c = values[(meta.sample_time / step - k0) as usize];
+        });
+    }
+    output
+}
+
+/// Analyze `signal`, transpose it by `semitones` (see `pitch_shift_with`) and synthesize the result
+pub fn pitch_shift_signal(g: &Gaborator, signal: &[f32], semitones: f64, params: &ShiftParams) -> Vec<f32> {
+    let mut coefs = Coefs::new(g);
+    g.analyze(signal, 0, &mut coefs);
+    let shifted = pitch_shift_with(g, &mut coefs, semitones, params);
+    let mut output = vec![0.0; signal.len()];
+    g.synthesize(&shifted, 0, &mut output);
+    output
+}
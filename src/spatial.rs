@@ -0,0 +1,160 @@
+//! Stereo spatial analysis: per-coefficient panning position, level and phase difference
+//! between the two channels, and a spectral panner rewriting them.
+//!
+//! Pan positions follow the constant-power panning law: a mono source panned to `pan`
+//! has left and right magnitudes proportional to `cos(θ)` and `sin(θ)`, where `θ = (pan + 1) * π / 4`.
+//! So -1 is hard left, 0 is center and 1 is hard right.
+
+use std::f32::consts::FRAC_PI_4;
+use std::io;
+
+use crate::{Coef, CoefMeta, Gaborator, Grid, MultiCoefs, SliceMeta, SpectralEffect};
+
+/// Spatial properties of the left and right coefficients at the same band and time
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Spatial {
+    /// Pan position from -1 (left) to 1 (right), 0 if both coefficients are zero
+    pub pan: f32,
+    /// Inter-channel level difference: level of right channel relative to the left one, in decibels.
+    /// Infinite if one of the coefficients is zero, NaN if both are.
+    pub ild_db: f32,
+    /// Inter-channel phase difference: phase of right channel minus phase of the left one, from -π to π
+    pub ipd: f32,
+    /// Combined magnitude, `sqrt(|left|² + |right|²)`
+    pub magnitude: f32,
+}
+
+impl Spatial {
+    /// Spatial properties of a pair of coefficients
+    pub fn new(left: Coef, right: Coef) -> Self {
+        let (l, r) = (left.norm(), right.norm());
+        Spatial {
+            pan: if l == 0.0 && r == 0.0 { 0.0 } else { r.atan2(l) / FRAC_PI_4 - 1.0 },
+            ild_db: 20.0 * (r / l).log10(),
+            ipd: (right * left.conj()).arg(),
+            magnitude: l.hypot(r),
+        }
+    }
+}
+
+/// Call `callback` with spatial properties of each pair of coefficients
+/// of two-channel `coefs` within the given range (same as in `Coefs::process`).
+pub fn analyze_stereo(
+    coefs: &mut MultiCoefs,
+    from_band: i32,
+    to_band: i32,
+    from_sample_time: i64,
+    to_sample_time: i64,
+    mut callback: impl FnMut(CoefMeta, Spatial),
+) {
+    assert_eq!(coefs.n_channels(), 2, "stereo coefficients should have two channels");
+    coefs.process_slices(from_band, to_band, from_sample_time, to_sample_time, |meta, channels| {
+        for (i, (l, r)) in channels[0].iter().zip(channels[1].iter()).enumerate() {
+            let sample_time = meta.sample_time + i as i64 * meta.time_step as i64;
+            callback(CoefMeta { band: meta.band, sample_time }, Spatial::new(*l, *r));
+        }
+    })
+}
+
+/// Spatial properties of left and right channels resampled to the same grid (see `Coefs::to_grid`).
+/// Panics if the grids have different layout.
+pub fn spatial_grid(left: &Grid<Coef>, right: &Grid<Coef>) -> Grid<Spatial> {
+    assert!(
+        left.band_begin == right.band_begin
+            && left.n_bands == right.n_bands
+            && left.time_begin == right.time_begin
+            && left.hop == right.hop
+            && left.n_frames == right.n_frames,
+        "left and right grids should have the same layout"
+    );
+    Grid {
+        band_begin: left.band_begin,
+        n_bands: left.n_bands,
+        time_begin: left.time_begin,
+        hop: left.hop,
+        n_frames: left.n_frames,
+        data: left.data.iter().zip(&right.data).map(|(l, r)| Spatial::new(*l, *r)).collect(),
+    }
+}
+
+/// Spectral panner: moves each time-frequency point of a stereo signal to the pan position given by a map.
+///
+/// The map is called with band's center frequency (as fraction of the sample rate),
+/// sample time and current spatial properties, and returns the target pan position from -1 to 1.
+/// Combined magnitude is kept, and each channel keeps its phase (or takes the other channel's phase, if it was silent),
+/// so the inter-channel phase difference is preserved.
+///
+/// Implements `SpectralEffect` for two-channel coefficients, so it can be prepared with `SpectralEffect::prepare`,
+/// combined with other effects in a `Chain` and applied offline or to a stereo `StreamingProcessor`.
+/// Applying it to any other number of channels fails with `InvalidInput` error.
+pub struct Panner<F> {
+    map: F,
+    band_ff: Vec<f64>,
+}
+
+impl<F: FnMut(f64, i64, &Spatial) -> f32> Panner<F> {
+    /// Create panner with given target pan map. It should be prepared before use.
+    pub fn new(map: F) -> Self {
+        Panner { map, band_ff: Vec::new() }
+    }
+
+    /// Rewrite a run of left and right coefficients (of equal length) of one band
+    fn process_stereo(&mut self, meta: SliceMeta, left: &mut [Coef], right: &mut [Coef]) {
+        assert!(!self.band_ff.is_empty(), "Panner should be prepared before use");
+        let ff = self.band_ff[meta.band as usize];
+        for (i, (l, r)) in left.iter_mut().zip(right.iter_mut()).enumerate() {
+            let spatial = Spatial::new(*l, *r);
+            if spatial.magnitude == 0.0 {
+                continue;
+            }
+            let sample_time = meta.sample_time + i as i64 * meta.time_step as i64;
+            let pan = (self.map)(ff, sample_time, &spatial).clamp(-1.0, 1.0);
+            let theta = (pan + 1.0) * FRAC_PI_4;
+
+            let (phase_l, phase_r) = match (l.norm() > 0.0, r.norm() > 0.0) {
+                (true, true) => (l.arg(), r.arg()),
+                (true, false) => (l.arg(), l.arg()),
+                _ => (r.arg(), r.arg()),
+            };
+            *l = Coef::from_polar(spatial.magnitude * theta.cos(), phase_l);
+            *r = Coef::from_polar(spatial.magnitude * theta.sin(), phase_r);
+        }
+    }
+
+    /// Apply to existing coefficients of two-channel `coefs` within the given time range.
+    /// Same as `MultiCoefs::apply_effect_range`.
+    pub fn apply(&mut self, coefs: &mut MultiCoefs, from_sample_time: i64, to_sample_time: i64) -> io::Result<()> {
+        coefs.apply_effect_range(from_sample_time, to_sample_time, self)
+    }
+}
+
+/// Works on two-channel coefficients only: `MultiCoefs::apply_effect` or
+/// `StreamingProcessor::process_interleaved_with_effect` of a two-channel processor.
+impl<F: FnMut(f64, i64, &Spatial) -> f32> SpectralEffect for Panner<F> {
+    /// Remember band frequencies of `g`, which coefficients processed later are produced with.
+    fn prepare(&mut self, g: &Gaborator) {
+        self.band_ff = (0..=g.band_lowpass()).map(|b| g.band_ff(b)).collect();
+    }
+
+    /// Leaves coefficients as is: a single channel has no spatial properties to rewrite,
+    /// and `check_channels` keeps it from being applied to one.
+    fn process(&mut self, _meta: SliceMeta, _coefs: &mut [Coef]) {}
+
+    /// Rewrites pairs of runs, leaving any other number of channels as is (rejected by `check_channels`).
+    fn process_channels(&mut self, meta: SliceMeta, channels: &mut [&mut [Coef]]) {
+        if let [left, right] = channels {
+            self.process_stereo(meta, left, right);
+        }
+    }
+
+    fn check_channels(&self, n_channels: usize) -> io::Result<()> {
+        if n_channels != 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Panner works on two channels, not {}", n_channels),
+            ));
+        }
+        Ok(())
+    }
+}
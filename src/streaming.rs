@@ -0,0 +1,278 @@
+use std::io;
+
+use crate::{Coef, CoefMeta, Coefs, Gaborator, MultiCoefs, SpectralEffect};
+
+/// Analyzes unbounded input supplied in blocks of arbitrary size.
+///
+/// Keeps track of the sample clock and hands out only coefficients that can no longer be changed
+/// by further input, i.e. ones older than current time minus `analysis_support_len`.
+/// Coefficients that have been handed out are forgotten, so memory usage stays bounded.
+pub struct StreamingAnalyzer<'a> {
+    g: &'a Gaborator,
+    coefs: Coefs,
+    sample_time: i64,
+    emitted_until: i64,
+}
+
+impl<'a> StreamingAnalyzer<'a> {
+    /// Create new streaming analyzer, with sample clock starting at `start_sample_time`.
+    pub fn new(g: &'a Gaborator, start_sample_time: i64) -> Self {
+        StreamingAnalyzer {
+            g,
+            coefs: Coefs::new(g),
+            sample_time: start_sample_time,
+            emitted_until: i64::MIN,
+        }
+    }
+
+    /// Time (in samples) of the next sample to be supplied to `push`.
+    pub fn sample_time(&self) -> i64 {
+        self.sample_time
+    }
+
+    /// Coefficients with sample times before this value have already been handed out.
+    pub fn finalized_until(&self) -> i64 {
+        self.sample_time - self.g.analysis_support_len() as i64
+    }
+
+    /// Analyze next block of samples, then call `callback` for each coefficient that became final.
+    ///
+    /// Within one call, coefficients are visited band by band, like in `Coefs::process`.
+    pub fn push(&mut self, samples: &[f32], callback: impl FnMut(CoefMeta, &mut Coef)) {
+        self.g.analyze(samples, self.sample_time, &mut self.coefs);
+        self.sample_time += samples.len() as i64;
+        let limit = self.finalized_until();
+        self.emit(limit, callback);
+    }
+
+    /// Hand out all remaining coefficients, including ones affected only by the tail of the input.
+    pub fn finish(mut self, callback: impl FnMut(CoefMeta, &mut Coef)) {
+        self.emit(i64::MAX, callback);
+    }
+
+    fn emit(&mut self, limit: i64, callback: impl FnMut(CoefMeta, &mut Coef)) {
+        if limit <= self.emitted_until {
+            return;
+        }
+        self.coefs.process(i32::MIN, i32::MAX, self.emitted_until, limit, callback);
+        self.emitted_until = limit;
+        if limit != i64::MAX {
+            self.coefs.forget_before(self.g, limit, false);
+        }
+    }
+}
+
+/// Block-based spectral effect processor for unbounded input.
+///
+/// Each input block is analyzed, the user callback gets to modify coefficients that became final,
+/// and a block of the same size of final output signal is synthesized.
+/// Output is delayed relative to input by constant [`latency`](StreamingProcessor::latency)
+/// (`analysis_support_len + synthesis_support_len` samples).
+/// Coefficients no longer needed for synthesis are forgotten, so memory usage stays bounded.
+///
+/// Processor created by [`new_realtime`](StreamingProcessor::new_realtime) does not allocate memory
+/// in steady state, which makes it usable inside audio callbacks.
+///
+/// Processor created by [`new_multichannel`](StreamingProcessor::new_multichannel) processes interleaved
+/// multichannel blocks, so that effects can see all channels at once (see `SpectralEffect::process_channels`).
+pub struct StreamingProcessor<'a> {
+    g: &'a Gaborator,
+    coefs: MultiCoefs,
+    sample_time: i64,
+    processed_until: i64,
+    recycler: Option<Recycler>,
+}
+
+impl<'a> StreamingProcessor<'a> {
+    /// Create new streaming processor, with input sample clock starting at `start_sample_time`.
+    pub fn new(g: &'a Gaborator, start_sample_time: i64) -> Self {
+        StreamingProcessor::new_multichannel(g, start_sample_time, 1)
+    }
+
+    /// Create new streaming processor for interleaved signal of `n_channels` channels,
+    /// with input sample clock starting at `start_sample_time`.
+    /// Blocks should be supplied to `process_interleaved_with_effect`.
+    pub fn new_multichannel(g: &'a Gaborator, start_sample_time: i64, n_channels: usize) -> Self {
+        assert!(n_channels > 0, "n_channels should be positive");
+        StreamingProcessor {
+            g,
+            coefs: MultiCoefs::new(g, n_channels),
+            sample_time: start_sample_time,
+            processed_until: i64::MIN,
+            recycler: None,
+        }
+    }
+
+    /// Create new streaming processor in real-time mode, with input sample clock starting at `start_sample_time`.
+    ///
+    /// Memory freed by Gaborator during processing (temporary buffers, forgotten coefficient slices)
+    /// is kept and reused instead of being returned to the allocator.
+    /// The processor is warmed up by processing silence in blocks of `max_block_size` samples before `start_sample_time`,
+    /// so that all buffers needed for blocks up to `max_block_size` samples are allocated in advance.
+    /// Feeding larger blocks works, but may allocate.
+    pub fn new_realtime(g: &'a Gaborator, start_sample_time: i64, max_block_size: usize) -> Self {
+        assert!(max_block_size > 0, "max_block_size should be positive");
+        let mut p = StreamingProcessor::new(g, start_sample_time);
+        p.recycler = Some(Recycler::new());
+
+        let warmup_blocks = 2 * p.latency() / max_block_size + 4;
+        p.sample_time -= (warmup_blocks * max_block_size) as i64;
+        let silence = vec![0.0; max_block_size];
+        let mut output = vec![0.0; max_block_size];
+        for _ in 0..warmup_blocks {
+            p.advance(&silence, &mut output, |_, _, _| ());
+        }
+        debug_assert_eq!(p.sample_time, start_sample_time);
+        p
+    }
+
+    /// Delay of output relative to input, in samples.
+    /// To get the tail of the processed signal, push this many samples of silence after the end of input.
+    pub fn latency(&self) -> usize {
+        self.g.analysis_support_len() + self.g.synthesis_support_len()
+    }
+
+    /// Number of interleaved channels in blocks supplied to the processor
+    pub fn n_channels(&self) -> usize {
+        self.coefs.n_channels()
+    }
+
+    /// Time (in samples) of the next input sample to be supplied to `process_block`.
+    pub fn sample_time(&self) -> i64 {
+        self.sample_time
+    }
+
+    /// Analyze `input`, call `callback` for each coefficient that became final, then synthesize `output`.
+    ///
+    /// `output` corresponds to time range of `input` shifted back by `latency()` samples.
+    ///
+    /// Panics if `input` and `output` have different lengths, or if the processor is multichannel.
+    pub fn process_block(
+        &mut self,
+        input: &[f32],
+        output: &mut [f32],
+        mut callback: impl FnMut(CoefMeta, &mut Coef),
+    ) {
+        assert_eq!(self.n_channels(), 1, "multichannel processor should be used with process_interleaved_with_effect");
+        // `process_slices` does not allocate, unlike `process`
+        self.advance(input, output, |coefs, from, to| {
+            coefs.channel_mut(0).process_slices(i32::MIN, i32::MAX, from, to, |meta, coefs| {
+                for (i, coef) in coefs.iter_mut().enumerate() {
+                    let sample_time = meta.sample_time + i as i64 * meta.time_step as i64;
+                    callback(CoefMeta { band: meta.band, sample_time }, coef);
+                }
+            })
+        })
+    }
+
+    /// Like `process_block`, but coefficients that became final are modified by `effect`.
+    /// The effect should have been prepared using the same `Gaborator`.
+    ///
+    /// Fails without processing the block if the effect does not work on a single channel,
+    /// see `SpectralEffect::check_channels`.
+    pub fn process_block_with_effect(
+        &mut self,
+        input: &[f32],
+        output: &mut [f32],
+        effect: &mut (impl SpectralEffect + ?Sized),
+    ) -> io::Result<()> {
+        assert_eq!(self.n_channels(), 1, "multichannel processor should be used with process_interleaved_with_effect");
+        effect.check_channels(1)?;
+        self.advance(input, output, |coefs, from, to| {
+            coefs.channel_mut(0).process_slices(i32::MIN, i32::MAX, from, to, |meta, coefs| effect.process(meta, coefs))
+        });
+        Ok(())
+    }
+
+    /// Like `process_block_with_effect`, but for interleaved blocks of a processor created by `new_multichannel`:
+    /// `input` and `output` contain `n_channels()` samples for each sample time.
+    /// Coefficients of all channels are passed to the effect together, see `SpectralEffect::process_channels`.
+    ///
+    /// Unlike single channel processing, this allocates temporary buffers even in real-time mode.
+    ///
+    /// Fails without processing the block if the effect does not work on `n_channels()` channels,
+    /// see `SpectralEffect::check_channels`.
+    pub fn process_interleaved_with_effect(
+        &mut self,
+        input: &[f32],
+        output: &mut [f32],
+        effect: &mut (impl SpectralEffect + ?Sized),
+    ) -> io::Result<()> {
+        assert!(
+            input.len().is_multiple_of(self.n_channels()),
+            "block size should be a multiple of number of channels"
+        );
+        effect.check_channels(self.n_channels())?;
+        self.advance(input, output, |coefs, from, to| {
+            coefs.process_slices(i32::MIN, i32::MAX, from, to, |meta, channels| effect.process_channels(meta, channels))
+        });
+        Ok(())
+    }
+
+    fn advance(
+        &mut self,
+        input: &[f32],
+        output: &mut [f32],
+        modify: impl FnOnce(&mut MultiCoefs, i64, i64),
+    ) {
+        assert_eq!(input.len(), output.len(), "input and output blocks should be of the same size");
+
+        let output_begin = self.sample_time - self.latency() as i64;
+
+        let _recycler_guard = self.recycler.as_mut().map(Recycler::activate);
+
+        // The interleaved versions allocate deinterleaving buffers, avoid them for a single channel
+        let n_channels = self.coefs.n_channels();
+        if n_channels == 1 {
+            self.g.analyze(input, self.sample_time, self.coefs.channel_mut(0));
+        } else {
+            self.g.analyze_interleaved(input, self.sample_time, &mut self.coefs);
+        }
+        self.sample_time += (input.len() / n_channels) as i64;
+
+        let limit = self.sample_time - self.g.analysis_support_len() as i64;
+        if limit > self.processed_until {
+            modify(&mut self.coefs, self.processed_until, limit);
+            self.processed_until = limit;
+        }
+
+        if n_channels == 1 {
+            self.g.synthesize(self.coefs.channel(0), output_begin, output);
+        } else {
+            self.g.synthesize_interleaved(&self.coefs, output_begin, output);
+        }
+
+        let output_end = output_begin + (output.len() / n_channels) as i64;
+        self.coefs.forget_before(self.g, output_end - self.g.synthesis_support_len() as i64, false);
+    }
+}
+
+/// Keeps memory blocks freed by Gaborator while active, to be reused for subsequent allocations.
+struct Recycler(gaborator_sys::cxx::UniquePtr<gaborator_sys::Recycler>);
+
+// Recycler is just a pool of memory blocks, not tied to any thread
+unsafe impl Send for Recycler {}
+
+impl Recycler {
+    fn new() -> Self {
+        Recycler(gaborator_sys::new_recycler())
+    }
+
+    /// Make Gaborator's allocations and deallocations on the current thread go through this recycler
+    /// until the returned guard is dropped, which reactivates the previously active one (if any),
+    /// e.g. of a processor whose effect runs another real-time processor.
+    fn activate(&mut self) -> RecyclerGuard<'_> {
+        gaborator_sys::activate_recycler(self.0.pin_mut());
+        RecyclerGuard(self)
+    }
+}
+
+/// Guards borrow their recyclers and are dropped in reverse order of activation,
+/// so the recycler reactivated on drop is still alive
+struct RecyclerGuard<'a>(&'a mut Recycler);
+
+impl Drop for RecyclerGuard<'_> {
+    fn drop(&mut self) {
+        gaborator_sys::deactivate_recycler(self.0 .0.pin_mut());
+    }
+}
@@ -0,0 +1,308 @@
+//! Time stretching: changing duration of a signal without changing its pitch.
+//!
+//! Coefficients of each band are resampled in time on the band's own time grid: magnitudes are interpolated
+//! linearly, while phase advances by the band's instantaneous frequency, measured from the phase difference
+//! of adjacent input coefficients (as in a phase vocoder). Since bass bands are long and treble bands are short,
+//! this trades time and frequency resolution per band instead of using one window size for everything.
+//! Where a band becomes audible, its phase starts from the input's phase extrapolated to the output time,
+//! on which all bands seeing a stationary partial agree.
+//!
+//! Transients are magnitude peaks after a sharp rise of energy, seen at the same time by several bands
+//! and not too quiet relative to the whole signal.
+//! Around them, coefficients are copied at the input rate instead of being stretched, centered on the stretched
+//! time of the transient, and the input position is held (or skipped, when shortening) before and after to meet it.
+//! All bands whose energy rises at the transient shift the copy by the same time, phase included, which keeps attacks sharp.
+//! Other bands, e.g. those of a partial sustained through the transient, are stretched as usual, since breaking
+//! their phase would make a dip as long as their filters.
+
+use std::f64::consts::{PI, TAU};
+use std::ops::Range;
+
+use crate::grid::read_band;
+use crate::{Coef, Coefs, Gaborator};
+
+/// Transients quieter than this, in decibels relative to the loudest coefficient of the signal, are ignored.
+/// Also the level, relative to the loudest coefficient of a band, below which the band is regarded as silent.
+const TRANSIENT_FLOOR_DB: f64 = -50.0;
+
+/// Settings of `time_stretch_with`
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StretchParams {
+    /// Rise of a band's energy, in decibels, over `transient_coefs` coefficients up to a magnitude peak,
+    /// regarded as a transient in that band. Infinity disables transient handling.
+    pub transient_db: f32,
+    /// Number of coefficients on each side of a transient copied without stretching.
+    /// Time steps of bands are proportional to their filters' durations, so this covers the same part
+    /// of a band's response in all bands.
+    pub transient_coefs: u32,
+    /// Minimum number of bands seeing a transient within one of their time steps from each other.
+    /// Onsets of single partials peak at different times in nearby bands and are stretched as usual.
+    pub transient_bands: u32,
+}
+
+impl Default for StretchParams {
+    fn default() -> Self {
+        StretchParams {
+            transient_db: 6.0,
+            transient_coefs: 8,
+            transient_bands: 8,
+        }
+    }
+}
+
+/// Coefficients of one band
+pub(crate) struct BandInput {
+    pub band: i32,
+    pub time_step: i64,
+    /// Index of the first coefficient
+    pub ci0: i64,
+    pub coefs: Vec<Coef>,
+    pub magnitudes: Vec<f64>,
+}
+
+/// Wrap phase to `-π..π`
+pub(crate) fn princarg(phase: f64) -> f64 {
+    (phase + PI).rem_euclid(TAU) - PI
+}
+
+/// Magnitude below which a band is regarded as silent
+pub(crate) fn silence_floor(magnitudes: &[f64]) -> f64 {
+    magnitudes.iter().cloned().fold(0.0, f64::max) * 10f64.powf(TRANSIENT_FLOOR_DB / 20.0)
+}
+
+/// Transients of one band as fractional coefficient indices: the largest magnitudes within `transient_coefs`
+/// coefficients on each side, rising by `transient_db` from `transient_coefs` coefficients before, and above `floor`.
+/// Positions are refined by parabolic interpolation.
+fn band_transients(magnitudes: &[f64], transient_db: f32, transient_coefs: u32, floor: f64) -> Vec<f64> {
+    let n = magnitudes.len();
+    let ratio = 10f64.powf(transient_db as f64 / 10.0);
+    let h = transient_coefs.max(1) as usize;
+    (1..n.saturating_sub(1))
+        .filter(|i| {
+            let m = magnitudes[*i];
+            let before = magnitudes[i.saturating_sub(h)];
+            m > floor
+                && m * m > ratio * before * before
+                && magnitudes[i.saturating_sub(h)..*i].iter().all(|x| *x < m)
+                && magnitudes[*i + 1..(*i + h + 1).min(n)].iter().all(|x| *x <= m)
+        })
+        .map(|i| {
+            let (a, b, c) = (magnitudes[i - 1], magnitudes[i], magnitudes[i + 1]);
+            let curvature = a - 2.0 * b + c;
+            i as f64 + if curvature < 0.0 { (0.5 * (a - c) / curvature).clamp(-0.5, 0.5) } else { 0.0 }
+        })
+        .collect()
+}
+
+/// Sample times of transients seen by at least `min_bands` bands, in increasing order.
+/// `candidates` are (time step, sample time) of transients of single bands.
+fn common_transients(mut candidates: Vec<(i64, f64)>, min_bands: u32) -> Vec<f64> {
+    // Bands with short time steps locate transients most precisely, so they go first
+    candidates.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    // (sample time, number of bands) sorted by time
+    let mut clusters: Vec<(f64, u32)> = Vec::new();
+    for (step, time) in candidates {
+        let i = clusters.partition_point(|c| c.0 < time);
+        let distance = |j: &usize| (clusters[*j].0 - time).abs();
+        let nearest = [i.checked_sub(1), Some(i).filter(|i| *i < clusters.len())]
+            .iter()
+            .flatten()
+            .cloned()
+            .filter(|j| distance(j) <= step as f64)
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)));
+        match nearest {
+            Some(j) => clusters[j].1 += 1,
+            None => clusters.insert(i, (time, 1)),
+        }
+    }
+    clusters
+        .into_iter()
+        .filter(|c| c.1 >= min_bands.max(1))
+        .map(|c| c.0)
+        .collect()
+}
+
+/// Read all existing coefficients, band by band
+pub(crate) fn read_bands(g: &Gaborator, coefs: &mut Coefs) -> Vec<BandInput> {
+    // Range of indices of existing coefficients, for each band
+    let mut ranges: Vec<Option<Range<i64>>> = vec![None; g.band_lowpass() as usize + 1];
+    coefs.process_slices(i32::MIN, i32::MAX, i64::MIN, i64::MAX, |meta, cs| {
+        let ci0 = meta.sample_time / meta.time_step as i64;
+        let ci1 = ci0 + cs.len() as i64;
+        let range = &mut ranges[meta.band as usize];
+        *range = Some(match range.take() {
+            Some(r) => r.start.min(ci0)..r.end.max(ci1),
+            None => ci0..ci1,
+        });
+    });
+    ranges
+        .into_iter()
+        .enumerate()
+        .filter_map(|(band, range)| {
+            let range = range?;
+            let band = band as i32;
+            let time_step = g.band_time_step(band);
+            let coefs = read_band(coefs, band, time_step, range.start, range.end);
+            Some(BandInput {
+                band,
+                time_step,
+                ci0: range.start,
+                magnitudes: coefs.iter().map(|c| c.norm() as f64).collect(),
+                coefs,
+            })
+        })
+        .collect()
+}
+
+/// Sample times of transients seen by at least `transient_bands` bands (see `StretchParams`), in increasing order
+pub(crate) fn transient_times(inputs: &[BandInput], transient_db: f32, transient_coefs: u32, transient_bands: u32) -> Vec<f64> {
+    // Not relative to each band's own loudest coefficient: bands far from all partials see only their leakage,
+    // which rises and falls like a transient in bands whose filters are about as long as the signal
+    let loudest: Vec<f64> = inputs.iter().flat_map(|input| input.magnitudes.iter().cloned().reduce(f64::max)).collect();
+    let floor = silence_floor(&loudest);
+    let candidates = inputs
+        .iter()
+        .flat_map(|input| {
+            band_transients(&input.magnitudes, transient_db, transient_coefs, floor)
+                .into_iter()
+                .map(move |p| (input.time_step, (input.ci0 as f64 + p) * input.time_step as f64))
+        })
+        .collect();
+    common_transients(candidates, transient_bands)
+}
+
+/// Those of `transients` (sample times) at which energy of the band rises by `transient_db` from `transient_coefs`
+/// coefficients before, as fractional coefficient indices of the band.
+/// Other bands, e.g. those of a partial sustained through a transient, keep their phase there.
+pub(crate) fn band_rises(input: &BandInput, transients: &[f64], transient_db: f32, transient_coefs: u32) -> Vec<f64> {
+    let ratio = 10f64.powf(transient_db as f64 / 10.0);
+    let h = transient_coefs.max(1) as f64;
+    let magnitude_at = |i: f64| if i >= 0.0 { input.magnitudes.get(i as usize).cloned().unwrap_or(0.0) } else { 0.0 };
+    transients
+        .iter()
+        .map(|t| t / input.time_step as f64 - input.ci0 as f64)
+        .filter(|p| {
+            let i = p.round();
+            let m = magnitude_at(i - 1.0).max(magnitude_at(i)).max(magnitude_at(i + 1.0));
+            let before = magnitude_at(i - h);
+            m * m > ratio * before * before
+        })
+        .collect()
+}
+
+/// Stretch coefficients of one band, `carrier` being the phase advance per time step at the band's center frequency
+/// and `transients` fractional coefficient indices of transients in increasing order.
+/// Returns index of the first output coefficient and the output.
+fn stretch_band(input: &BandInput, carrier: f64, factor: f64, transients: &[f64], params: &StretchParams) -> (i64, Vec<Coef>) {
+    let ci0 = input.ci0;
+    let magnitudes = &input.magnitudes;
+    let n = magnitudes.len();
+    let phases: Vec<f64> = input.coefs.iter().map(|c| c.arg() as f64).collect();
+    // Phase advance between coefficients `i` and `i + 1`: coefficients are demodulated, so this is
+    // the offset of the instantaneous frequency from the band's center frequency
+    let advances: Vec<f64> = phases.windows(2).map(|p| princarg(p[1] - p[0])).collect();
+    let advance_at = |x: f64| {
+        let j = x.floor().max(0.0) as usize;
+        advances.get(j.min(n.saturating_sub(2))).cloned().unwrap_or(0.0)
+    };
+    let half = params.transient_coefs.max(1) as f64;
+    let floor = silence_floor(magnitudes);
+    let output_index = |p: f64| (ci0 as f64 + p) * factor;
+
+    let k0 = (ci0 as f64 * factor).floor() as i64;
+    let k1 = ((ci0 + n as i64 - 1) as f64 * factor).ceil() as i64 + 1;
+    let magnitude_at = |i: i64| if i >= 0 && (i as usize) < n { magnitudes[i as usize] } else { 0.0 };
+
+    let mut output = Vec::with_capacity((k1 - k0) as usize);
+    let mut phase = 0.0;
+    let mut previous: Option<(f64, Option<usize>)> = None;
+    let mut nearest = 0;
+    for k in k0..k1 {
+        let stretched = k as f64 / factor - ci0 as f64;
+
+        // Around the nearest transient, input runs at its own rate, centered on the transient's stretched position.
+        // Before and after, input position is held (when lengthening) or skipped (when shortening) to meet it.
+        while nearest + 1 < transients.len() && output_index(transients[nearest + 1]) <= k as f64 {
+            nearest += 1;
+        }
+        let distance = |t: &usize| (output_index(transients[*t]) - k as f64).abs();
+        let transient = (nearest..transients.len().min(nearest + 2)).min_by(|a, b| distance(a).total_cmp(&distance(b)));
+        let (u, region) = match transient {
+            Some(t) => {
+                let p = transients[t];
+                let locked = p + (k as f64 - output_index(p));
+                let (start, end) = (p - half, p + half);
+                let inside = locked >= start && locked <= end;
+                let u = if factor >= 1.0 {
+                    locked.clamp(stretched.min(start), stretched.max(end))
+                } else if inside {
+                    locked
+                } else {
+                    stretched
+                };
+                (u, if inside { Some(t) } else { None })
+            }
+            None => (stretched, None),
+        };
+
+        let lower = u.floor();
+        let frac = u - lower;
+        let magnitude = magnitude_at(lower as i64) * (1.0 - frac) + magnitude_at(lower as i64 + 1) * frac;
+        let i = lower.max(0.0).min((n - 1) as f64) as usize;
+        phase = match previous {
+            // Transient region is a copy of the input shifted by the same time in all bands. Shifting a signal
+            // rotates demodulated coefficients by the band's center frequency times the shift.
+            _ if region.is_some() && previous.is_none_or(|(_, last_region)| region != last_region) => {
+                let shift = (k - ci0) as f64 - u;
+                (phases[i] + advance_at(u) * (u - i as f64) - carrier * shift).rem_euclid(TAU)
+            }
+            // Input phase at coefficient `i` extrapolated to output time, so that all bands agree on stationary partials
+            None => (phases[i] + advance_at(u) * (k - ci0 - i as i64) as f64).rem_euclid(TAU),
+            // Output advances by one time step, whatever the input position does
+            Some((last_u, _)) => (phase + advance_at((last_u + u) / 2.0)).rem_euclid(TAU),
+        };
+        // Phase accumulated through silence means nothing, so it starts over when the band becomes audible
+        previous = if magnitude > floor { Some((u, region)) } else { None };
+        output.push(Coef::from_polar(magnitude as f32, phase as f32));
+    }
+    (k0, output)
+}
+
+/// Stretch existing coefficients in time by `factor` (2 makes the signal twice as long) without changing pitch.
+/// Sample time 0 stays in place. Uses default `StretchParams`.
+pub fn time_stretch(g: &Gaborator, coefs: &mut Coefs, factor: f64) -> Coefs {
+    time_stretch_with(g, coefs, factor, &StretchParams::default())
+}
+
+/// Like `time_stretch`, with given settings
+pub fn time_stretch_with(g: &Gaborator, coefs: &mut Coefs, factor: f64, params: &StretchParams) -> Coefs {
+    assert!(factor > 0.0 && factor.is_finite(), "stretch factor should be positive");
+
+    let inputs = read_bands(g, coefs);
+    let transients = transient_times(&inputs, params.transient_db, params.transient_coefs, params.transient_bands);
+
+    let mut output = Coefs::new(g);
+    for input in &inputs {
+        let (band, step) = (input.band, input.time_step);
+        let carrier = TAU * g.band_ff(band) * step as f64;
+        let band_transients = band_rises(input, &transients, params.transient_db, params.transient_coefs);
+        let (k0, values) = stretch_band(input, carrier, factor, &band_transients, params);
+        let k1 = k0 + values.len() as i64;
+        output.fill(band, band + 1, k0 * step, k1 * step, |meta, c| {
+            *c = values[(meta.sample_time / step - k0) as usize];
+        });
+    }
+    output
+}
+
+/// Analyze `signal`, stretch it by `factor` (see `time_stretch`) and synthesize the result,
+/// which has `signal.len() * factor` samples.
+pub fn time_stretch_signal(g: &Gaborator, signal: &[f32], factor: f64) -> Vec<f32> {
+    let mut coefs = Coefs::new(g);
+    g.analyze(signal, 0, &mut coefs);
+    let stretched = time_stretch(g, &mut coefs, factor);
+    let mut output = vec![0.0; (signal.len() as f64 * factor).round() as usize];
+    g.synthesize(&stretched, 0, &mut output);
+    output
+}
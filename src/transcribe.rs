@@ -0,0 +1,250 @@
+//! Polyphonic note transcription: turning coefficients into note events with onset, offset, pitch and velocity.
+//!
+//! Each frame's band magnitudes (see `Coefs::to_energy_grid`) are searched for spectral peaks,
+//! located between bands by parabolic interpolation. Peaks are grouped into harmonic series from the lowest up:
+//! a peak near an integer multiple of a lower unassigned peak joins its series, unless it is much louder
+//! than that fundamental. Each remaining series is a note candidate whose strength is the sum of its peaks' magnitudes.
+//!
+//! Candidates are then tracked per MIDI note over frames with hysteresis: a note starts when its strength
+//! reaches `on_threshold_db`, lasts while it stays above `off_threshold_db` (bridging short gaps)
+//! and restarts on a sudden rise of strength after it has decayed. Note events can be written as MIDI files using `midi` module.
+//!
+//! Needs the analyzer to know the sample rate (see `Gaborator::with_sample_rate`).
+
+use std::io;
+use std::ops::Range;
+
+use crate::notes::Note;
+use crate::{Coefs, Gaborator};
+
+/// Number of MIDI notes
+const N_KEYS: usize = 128;
+
+/// Settings of `transcribe`
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TranscribeParams {
+    /// Distance between frames, in samples
+    pub hop: u32,
+    /// Range of fundamental frequencies of notes, in Hz
+    pub hz_range: Range<f64>,
+    /// Spectral peaks weaker than this, in decibels relative to the loudest band of the whole signal, are ignored
+    pub peak_threshold_db: f32,
+    /// Number of harmonics, including the fundamental, grouped into a note
+    pub n_harmonics: usize,
+    /// Maximum deviation of a peak from an exact harmonic frequency, in cents
+    pub harmonic_tolerance_cents: f64,
+    /// A peak at a harmonic frequency louder than its fundamental by more than this factor is a separate note
+    pub max_harmonic_ratio: f32,
+    /// Strength, in decibels relative to the strongest note candidate of the whole signal, starting a note.
+    /// Should be negative; velocity goes from 1 at this strength to 127 at the strongest note candidate.
+    pub on_threshold_db: f32,
+    /// Strength, in decibels relative to the strongest note candidate, below which a note ends
+    pub off_threshold_db: f32,
+    /// Rise of strength between adjacent frames, in decibels, starting the same note again
+    /// once the strength has fallen at least as much below the note's maximum (so not during its attack)
+    pub reonset_db: f32,
+    /// Notes shorter than this number of frames are dropped
+    pub min_frames: usize,
+    /// Gaps of at most this number of frames within a note are bridged
+    pub max_gap_frames: usize,
+}
+
+impl Default for TranscribeParams {
+    fn default() -> Self {
+        TranscribeParams {
+            hop: 512,
+            hz_range: 27.5..4200.0,
+            peak_threshold_db: -50.0,
+            n_harmonics: 8,
+            harmonic_tolerance_cents: 35.0,
+            max_harmonic_ratio: 2.0,
+            on_threshold_db: -30.0,
+            off_threshold_db: -36.0,
+            reonset_db: 6.0,
+            min_frames: 3,
+            max_gap_frames: 2,
+        }
+    }
+}
+
+/// Transcribed note
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NoteEvent {
+    /// Sample time of the note start
+    pub onset: i64,
+    /// Sample time of the note end
+    pub offset: i64,
+    /// MIDI note (from 0 to 127) with mean deviation of the fundamental from it
+    pub note: Note,
+    /// MIDI velocity from 1 to 127, derived from the note's maximum strength
+    pub velocity: u8,
+}
+
+/// Spectral peak within a frame
+struct Peak {
+    hz: f64,
+    magnitude: f32,
+}
+
+/// Note candidates of one frame: (strength, cents) for each MIDI note
+type Activations = Vec<Option<(f32, f64)>>;
+
+/// Strongest harmonic series per MIDI note, from peaks sorted by increasing frequency
+fn group_harmonics(peaks: &[Peak], params: &TranscribeParams) -> Activations {
+    let mut activations = vec![None; N_KEYS];
+    let mut assigned = vec![false; peaks.len()];
+    for i in 0..peaks.len() {
+        let fundamental = &peaks[i];
+        if assigned[i] || fundamental.hz >= params.hz_range.end {
+            continue;
+        }
+        let mut strength = fundamental.magnitude;
+        for (j, peak) in peaks.iter().enumerate().skip(i + 1) {
+            let ratio = peak.hz / fundamental.hz;
+            let h = ratio.round();
+            if assigned[j] || h < 2.0 || h > params.n_harmonics as f64 {
+                continue;
+            }
+            let cents = 1200.0 * (ratio / h).log2();
+            if cents.abs() <= params.harmonic_tolerance_cents
+                && peak.magnitude <= fundamental.magnitude * params.max_harmonic_ratio
+            {
+                assigned[j] = true;
+                strength += peak.magnitude;
+            }
+        }
+
+        let note = Note::from_hz(fundamental.hz);
+        if (0..N_KEYS as i32).contains(&note.midi) {
+            let slot = &mut activations[note.midi as usize];
+            if slot.is_none_or(|(s, _)| s < strength) {
+                *slot = Some((strength, note.cents));
+            }
+        }
+    }
+    activations
+}
+
+/// Note being tracked
+struct Active {
+    start: usize,
+    last_on: usize,
+    max_db: f32,
+    cents_sum: f64,
+    n_on: usize,
+}
+
+/// Transcribe notes sounding within frames every `params.hop` samples,
+/// starting from `time_range.start` and not reaching `time_range.end`.
+/// Events are sorted by onset, then by note.
+///
+/// Returns `InvalidInput` error if the analyzer does not know the sample rate, or `on_threshold_db` is not negative.
+pub fn transcribe(g: &Gaborator, coefs: &mut Coefs, time_range: Range<i64>, params: &TranscribeParams) -> io::Result<Vec<NoteEvent>> {
+    let sample_rate = g.require_sample_rate()?;
+    // Velocity is scaled by it
+    if params.on_threshold_db >= 0.0 || params.on_threshold_db.is_nan() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("note on threshold should be negative, not {} dB", params.on_threshold_db),
+        ));
+    }
+    // Harmonics of the highest notes may be above the range
+    let top_hz = params.hz_range.end * params.n_harmonics.max(1) as f64;
+    let bands = g.bands_for_ff_range(params.hz_range.start / sample_rate..top_hz / sample_rate);
+    let bands = bands.start..bands.end.min(g.bandpass_bands_end());
+    let magnitudes = coefs.to_energy_grid(g, params.hop, time_range, bands).map(|e| e.sqrt());
+    let n_frames = magnitudes.n_frames;
+
+    let max_magnitude = magnitudes.data.iter().cloned().fold(0.0, f32::max);
+    let floor = max_magnitude * 10f32.powf(params.peak_threshold_db / 20.0);
+    let mut column = vec![0.0; magnitudes.n_bands];
+    let mut peaks = Vec::new();
+    let frames: Vec<Activations> = (0..n_frames)
+        .map(|f| {
+            for (b, m) in column.iter_mut().enumerate() {
+                *m = magnitudes.data[b * n_frames + f];
+            }
+            peaks.clear();
+            for b in 1..column.len().saturating_sub(1) {
+                let (a, m, c) = (column[b - 1], column[b], column[b + 1]);
+                if m < floor || m <= a || m < c {
+                    continue;
+                }
+                // Parabolic interpolation between adjacent bands
+                let curvature = a - 2.0 * m + c;
+                let offset = if curvature < 0.0 { (0.5 * (a - c) / curvature).clamp(-0.5, 0.5) } else { 0.0 };
+                let hz = g.ff_for_band_position((magnitudes.band_begin + b as i32) as f64 + offset as f64) * sample_rate;
+                if hz >= params.hz_range.start {
+                    peaks.push(Peak { hz, magnitude: m });
+                }
+            }
+            // Bands go from high to low frequencies
+            peaks.reverse();
+            group_harmonics(&peaks, params)
+        })
+        .collect();
+
+    let max_strength = frames.iter().flatten().flatten().map(|(s, _)| *s).fold(0.0, f32::max);
+    let mut events = Vec::new();
+    let close = |key: usize, note: &Active, end: usize, events: &mut Vec<NoteEvent>| {
+        if end - note.start < params.min_frames.max(1) {
+            return;
+        }
+        let velocity = 127.0 * (1.0 + note.max_db / -params.on_threshold_db);
+        events.push(NoteEvent {
+            onset: magnitudes.frame_time(note.start),
+            offset: magnitudes.frame_time(end),
+            note: Note {
+                midi: key as i32,
+                cents: note.cents_sum / note.n_on as f64,
+            },
+            velocity: velocity.round().clamp(1.0, 127.0) as u8,
+        });
+    };
+    for key in 0..N_KEYS {
+        let mut active: Option<Active> = None;
+        let mut previous_db = f32::NEG_INFINITY;
+        for (f, frame) in frames.iter().enumerate() {
+            let (db, cents) = match frame[key] {
+                Some((s, cents)) => (20.0 * (s / max_strength).log10(), cents),
+                None => (f32::NEG_INFINITY, 0.0),
+            };
+            if let Some(note) = &mut active {
+                if db >= params.off_threshold_db {
+                    if db - previous_db >= params.reonset_db
+                        && previous_db <= note.max_db - params.reonset_db
+                        && f - note.start >= params.min_frames
+                    {
+                        close(key, note, f, &mut events);
+                        active = None;
+                    } else {
+                        note.last_on = f;
+                        note.max_db = note.max_db.max(db);
+                        note.cents_sum += cents;
+                        note.n_on += 1;
+                    }
+                } else if f - note.last_on > params.max_gap_frames {
+                    close(key, note, note.last_on + 1, &mut events);
+                    active = None;
+                }
+            }
+            if active.is_none() && db >= params.on_threshold_db {
+                active = Some(Active {
+                    start: f,
+                    last_on: f,
+                    max_db: db,
+                    cents_sum: cents,
+                    n_on: 1,
+                });
+            }
+            previous_db = db;
+        }
+        if let Some(note) = &active {
+            close(key, note, note.last_on + 1, &mut events);
+        }
+    }
+    events.sort_by_key(|e| (e.onset, e.note.midi));
+    Ok(events)
+}
@@ -0,0 +1,114 @@
+use std::io;
+use std::ops::Range;
+
+use crate::{Coef, CoefMeta, Coefs, Gaborator};
+
+impl Gaborator {
+    /// Sample rate, or `InvalidInput` error for analyzers that do not know it
+    pub(crate) fn require_sample_rate(&self) -> io::Result<f64> {
+        self.sample_rate.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Gaborator should be created using `with_sample_rate` to use Hz and seconds",
+            )
+        })
+    }
+
+    /// Center frequency of band number `band`, in Hz. The lowpass band has center frequency 0.
+    ///
+    /// Returns `None` if the analyzer does not know the sample rate; same for other Hz and seconds based methods.
+    pub fn band_hz(&self, band: i32) -> Option<f64> {
+        Some(self.band_ff(band) * self.sample_rate?)
+    }
+
+    /// Bandpass band with center frequency closest to `hz` on logarithmic scale.
+    /// Frequencies outside of the filter bank's range give the highest or lowest bandpass band;
+    /// zero or negative frequencies give the lowpass band.
+    pub fn band_for_hz(&self, hz: f64) -> Option<i32> {
+        Some(self.band_for_ff(hz / self.sample_rate?))
+    }
+
+    /// Like `band_for_hz`, with frequency in units of the sample rate
+    pub(crate) fn band_for_ff(&self, ff: f64) -> i32 {
+        if ff <= 0.0 {
+            return self.band_lowpass();
+        }
+        let position = self.band_position_for_ff(ff);
+        let last = self.bandpass_bands_end() - 1;
+        (position.round().max(self.bandpass_bands_begin() as f64).min(last as f64)) as i32
+    }
+
+    /// Fractional band number at which a bandpass band would be centered on `ff` (in units of the sample rate),
+    /// which should be positive. Not limited to the bands that exist.
+    pub(crate) fn band_position_for_ff(&self, ff: f64) -> f64 {
+        self.band_ref() as f64 + self.params.bands_per_octave as f64 * (self.params.ff_ref / ff).log2()
+    }
+
+    /// Inverse of `band_position_for_ff`
+    pub(crate) fn ff_for_band_position(&self, position: f64) -> f64 {
+        let octaves = (self.band_ref() as f64 - position) / self.params.bands_per_octave as f64;
+        self.params.ff_ref * octaves.exp2()
+    }
+
+    /// Range of band numbers whose center frequencies are within `hz` range (`start` inclusive, `end` exclusive).
+    /// The lowpass band is included if the range contains 0.
+    pub fn bands_for_hz_range(&self, hz: Range<f64>) -> Option<Range<i32>> {
+        let sample_rate = self.sample_rate?;
+        Some(self.bands_for_ff_range(hz.start / sample_rate..hz.end / sample_rate))
+    }
+
+    /// Like `bands_for_hz_range`, with frequencies in units of the sample rate
+    pub(crate) fn bands_for_ff_range(&self, ff: Range<f64>) -> Range<i32> {
+        let begin = self.first_band_below_ff(ff.end);
+        let end = self.first_band_below_ff(ff.start);
+        begin..end.max(begin)
+    }
+
+    /// First band with center frequency below `ff`, or one past the lowpass band if there is none.
+    /// Band frequencies decrease with band number, ending with 0 of the lowpass band, so binary search applies.
+    fn first_band_below_ff(&self, ff: f64) -> i32 {
+        let (mut begin, mut end) = (self.bandpass_bands_begin(), self.band_lowpass() + 1);
+        while begin < end {
+            let middle = begin + (end - begin) / 2;
+            if self.band_ff(middle) >= ff {
+                begin = middle + 1;
+            } else {
+                end = middle;
+            }
+        }
+        begin
+    }
+
+    /// Convert time in seconds to the nearest sample time
+    pub fn seconds_to_sample_time(&self, seconds: f64) -> Option<i64> {
+        Some((seconds * self.sample_rate?).round() as i64)
+    }
+
+    /// Convert sample time to seconds
+    pub fn sample_time_to_seconds(&self, sample_time: i64) -> Option<f64> {
+        Some(sample_time as f64 / self.sample_rate?)
+    }
+}
+
+impl Coefs {
+    /// Like `process`, but selects coefficients by time range in seconds and frequency range in Hz,
+    /// using sample rate remembered by `g` (see `Gaborator::bands_for_hz_range`).
+    /// Infinite bounds mean no limit, so `f64::NEG_INFINITY..f64::INFINITY` selects everything.
+    ///
+    /// Returns `InvalidInput` error if `g` does not know the sample rate.
+    pub fn process_seconds_hz(
+        &mut self,
+        g: &Gaborator,
+        seconds: Range<f64>,
+        hz: Range<f64>,
+        callback: impl FnMut(CoefMeta, &mut Coef),
+    ) -> io::Result<()> {
+        let sample_rate = g.require_sample_rate()?;
+        let bands = g.bands_for_ff_range(hz.start / sample_rate..hz.end / sample_rate);
+        if !bands.is_empty() {
+            let time = |seconds: f64| (seconds * sample_rate).round() as i64;
+            self.process(bands.start, bands.end, time(seconds.start), time(seconds.end), callback);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+//! Reading and writing WAV files, based on `hound` crate.
+//!
+//! Integer samples are scaled according to their bit depth, so full scale of any format maps to `-1.0..1.0`.
+
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::{Coefs, Gaborator, GaboratorCache, GaboratorParams, MultiCoefs};
+
+fn convert_error(e: hound::Error) -> io::Error {
+    match e {
+        hound::Error::IoError(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+    }
+}
+
+/// Sample format of a written WAV file
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SampleFormat {
+    /// 8-bit unsigned integer
+    Int8,
+    /// 16-bit signed integer
+    Int16,
+    /// 24-bit signed integer
+    Int24,
+    /// 32-bit signed integer
+    Int32,
+    /// 32-bit IEEE float
+    Float32,
+}
+
+/// Multiplier to convert integer samples of given bit depth to `f32` samples in `-1.0..1.0` range
+pub fn int_sample_scale(bits_per_sample: u16) -> f32 {
+    1.0 / (1u64 << (bits_per_sample - 1)) as f32
+}
+
+/// Decoded audio with separate buffer for each channel
+#[derive(Clone, Debug, PartialEq)]
+pub struct WavAudio {
+    /// Sample rate in Hz
+    pub sample_rate: u32,
+    /// Samples of each channel; all channels have the same length
+    pub channels: Vec<Vec<f32>>,
+}
+
+impl WavAudio {
+    /// Number of samples in each channel
+    pub fn len(&self) -> usize {
+        self.channels.first().map_or(0, |c| c.len())
+    }
+
+    /// Whether there are no samples
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Read all samples of WAV file, converting them to `f32`.
+pub fn read_wav(path: impl AsRef<Path>) -> io::Result<WavAudio> {
+    read_wav_from(io::BufReader::new(std::fs::File::open(path)?))
+}
+
+/// Like `read_wav`, but reads from arbitrary (preferably buffered) reader.
+pub fn read_wav_from(r: impl Read) -> io::Result<WavAudio> {
+    let reader = hound::WavReader::new(r).map_err(convert_error)?;
+    let spec = reader.spec();
+    let n_channels = spec.channels as usize;
+    let mut channels: Vec<Vec<f32>> = (0..n_channels)
+        .map(|_| Vec::with_capacity(reader.duration() as usize))
+        .collect();
+
+    let mut push = |i: usize, x: f32| channels[i % n_channels].push(x);
+    match spec.sample_format {
+        hound::SampleFormat::Float => {
+            for (i, s) in reader.into_samples::<f32>().enumerate() {
+                push(i, s.map_err(convert_error)?);
+            }
+        }
+        hound::SampleFormat::Int => {
+            let scale = int_sample_scale(spec.bits_per_sample);
+            for (i, s) in reader.into_samples::<i32>().enumerate() {
+                push(i, s.map_err(convert_error)? as f32 * scale);
+            }
+        }
+    }
+
+    // Drop incomplete trailing frame, if any
+    let len = channels.iter().map(|c| c.len()).min().unwrap_or(0);
+    for c in &mut channels {
+        c.truncate(len);
+    }
+    Ok(WavAudio {
+        sample_rate: spec.sample_rate,
+        channels,
+    })
+}
+
+/// Write audio to WAV file in given sample format.
+/// Integer formats are clipped to full scale.
+pub fn write_wav(path: impl AsRef<Path>, audio: &WavAudio, format: SampleFormat) -> io::Result<()> {
+    assert!(!audio.channels.is_empty(), "audio should have at least one channel");
+    assert!(
+        audio.channels.iter().all(|c| c.len() == audio.len()),
+        "all channels should have the same length"
+    );
+    let (bits_per_sample, sample_format) = match format {
+        SampleFormat::Int8 => (8, hound::SampleFormat::Int),
+        SampleFormat::Int16 => (16, hound::SampleFormat::Int),
+        SampleFormat::Int24 => (24, hound::SampleFormat::Int),
+        SampleFormat::Int32 => (32, hound::SampleFormat::Int),
+        SampleFormat::Float32 => (32, hound::SampleFormat::Float),
+    };
+    let spec = hound::WavSpec {
+        channels: audio.channels.len() as u16,
+        sample_rate: audio.sample_rate,
+        bits_per_sample,
+        sample_format,
+    };
+    let mut w = hound::WavWriter::create(path, spec).map_err(convert_error)?;
+    let max = (1i64 << (bits_per_sample - 1)) - 1;
+    let scale = 1.0 / int_sample_scale(bits_per_sample) as f64;
+    for i in 0..audio.len() {
+        for c in &audio.channels {
+            let x = c[i];
+            match format {
+                SampleFormat::Float32 => w.write_sample(x),
+                _ => w.write_sample((x as f64 * scale).round().max(-max as f64 - 1.0).min(max as f64) as i32),
+            }
+            .map_err(convert_error)?;
+        }
+    }
+    w.finalize().map_err(convert_error)
+}
+
+/// Result of `analyze_wav`: coefficients of each channel together with the analyzer that produced them
+pub struct AnalyzedWav {
+    /// Analyzer for the parameters given to `analyze_wav` and the file's sample rate, shared through `GaboratorCache`
+    pub gaborator: Arc<Gaborator>,
+    /// Sample rate of the file in Hz
+    pub sample_rate: u32,
+    /// Number of samples in each channel of the file
+    pub len: usize,
+    /// Coefficients of each channel
+    pub coefs: MultiCoefs,
+}
+
+impl AnalyzedWav {
+    /// Resynthesize audio of the original length from (possibly modified) coefficients.
+    pub fn synthesize(&self) -> WavAudio {
+        WavAudio {
+            sample_rate: self.sample_rate,
+            channels: self
+                .coefs
+                .channels()
+                .iter()
+                .map(|coefs| {
+                    let mut signal = vec![0.0; self.len];
+                    self.gaborator.synthesize(coefs, 0, &mut signal);
+                    signal
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Read WAV file and analyze each of its channels, starting at sample time 0.
+pub fn analyze_wav(path: impl AsRef<Path>, params: &GaboratorParams) -> io::Result<AnalyzedWav> {
+    let audio = read_wav(path)?;
+    let g = GaboratorCache::get_with_sample_rate(params, audio.sample_rate as f64);
+    let coefs = MultiCoefs::from_channels(
+        audio
+            .channels
+            .iter()
+            .map(|signal| {
+                let mut coefs = Coefs::new(&g);
+                g.analyze(signal, 0, &mut coefs);
+                coefs
+            })
+            .collect(),
+    );
+    Ok(AnalyzedWav {
+        gaborator: g,
+        sample_rate: audio.sample_rate,
+        len: audio.len(),
+        coefs,
+    })
+}
@@ -0,0 +1,50 @@
+use gaborator::beat::{detect_beats, BeatParams};
+use gaborator::onset::OnsetParams;
+use gaborator::{Coefs, Gaborator, GaboratorParams};
+
+const SAMPLE_RATE: f64 = 48000.0;
+const LEN: usize = 8 * SAMPLE_RATE as usize;
+
+fn analyzer() -> Gaborator {
+    Gaborator::with_sample_rate(&GaboratorParams::for_sample_rate(SAMPLE_RATE, 30.0, 440.0, 12), SAMPLE_RATE)
+}
+
+/// Short decaying bursts of noise every `period` samples, starting at `first`
+fn click_train(first: usize, period: f64) -> Vec<f32> {
+    let mut signal = vec![0.0; LEN];
+    let mut noise = 12345u32;
+    let mut click = first as f64;
+    while (click as usize) + 480 < LEN {
+        for (i, x) in signal[click as usize..click as usize + 480].iter_mut().enumerate() {
+            // Linear congruential generator, for noise independent of external crates
+            noise = noise.wrapping_mul(1664525).wrapping_add(1013904223);
+            *x += ((noise >> 8) as f32 / (1 << 24) as f32 - 0.5) * (-(i as f32) / 96.0).exp();
+        }
+        click += period;
+    }
+    signal
+}
+
+#[test]
+fn tempo_and_beats_of_click_train() {
+    let g = analyzer();
+    let onset_params = OnsetParams::default();
+    let first = 24000;
+    // Away from the 120 BPM prior, and not simple ratios of it
+    for bpm in [90.0, 100.0, 137.0, 170.0] {
+        let period = 60.0 * SAMPLE_RATE / bpm;
+        let signal = click_train(first, period);
+        let mut coefs = Coefs::new(&g);
+        g.analyze(&signal, 0, &mut coefs);
+        let beats = detect_beats(&g, &mut coefs, 0..LEN as i64, &onset_params, &BeatParams::default()).unwrap();
+
+        assert!((beats.bpm / bpm - 1.0).abs() < 0.005, "{} BPM detected as {}", bpm, beats.bpm);
+        let n_clicks = ((LEN - 480 - first) as f64 / period).ceil() as usize;
+        assert_eq!(beats.sample_times.len(), n_clicks, "{} BPM: {:?}", bpm, beats.sample_times);
+        for (i, t) in beats.sample_times.iter().enumerate() {
+            let click = (first as f64 + i as f64 * period) as i64;
+            assert!((t - click).abs() <= onset_params.hop as i64, "{} BPM: beat at {} for click at {}", bpm, t, click);
+            assert_eq!(beats.seconds[i], *t as f64 / SAMPLE_RATE);
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::io::ErrorKind;
+
+use gaborator::chroma::{chroma, ChromaParams};
+use gaborator::{Coefs, Gaborator, GaboratorParams};
+
+const SAMPLE_RATE: f64 = 48000.0;
+const LEN: usize = SAMPLE_RATE as usize;
+
+fn analyzer() -> Gaborator {
+    Gaborator::with_sample_rate(&GaboratorParams::for_sample_rate(SAMPLE_RATE, 50.0, 440.0, 36), SAMPLE_RATE)
+}
+
+fn analyzed(g: &Gaborator, hz: f64) -> Coefs {
+    let signal: Vec<f32> = (0..LEN)
+        .map(|i| (i as f64 * hz / SAMPLE_RATE * std::f64::consts::TAU).sin() as f32 * 0.5)
+        .collect();
+    let mut coefs = Coefs::new(g);
+    g.analyze(&signal, 0, &mut coefs);
+    coefs
+}
+
+/// Pitch class with the most energy in the middle frame, and the largest share of energy in other classes
+fn dominant_class(frames: &[[f32; 12]]) -> (usize, f32) {
+    let frame = frames[frames.len() / 2];
+    let (class, _) = frame.iter().enumerate().fold((0, 0.0), |best, (i, x)| if *x > best.1 { (i, *x) } else { best });
+    let leakage = frame.iter().enumerate().filter(|(i, _)| *i != class).map(|(_, x)| *x).fold(0.0, f32::max);
+    (class, leakage)
+}
+
+#[test]
+fn pure_a4_lands_in_pitch_class_9() {
+    let g = analyzer();
+    let mut coefs = analyzed(&g, 440.0);
+    let frames = chroma(&g, &mut coefs, 1024, 0..LEN as i64, &ChromaParams::default()).unwrap();
+    assert_eq!(frames.len(), LEN / 1024 + 1);
+    let (class, leakage) = dominant_class(&frames);
+    assert_eq!(class, 9);
+    assert_eq!(frames[frames.len() / 2][9], 1.0, "max normalization");
+    assert!(leakage < 0.1, "leakage {}", leakage);
+}
+
+#[test]
+fn pitch_classes_follow_tuning() {
+    let g = analyzer();
+    let mut coefs = analyzed(&g, 261.6256);
+    let frames = chroma(&g, &mut coefs, 1024, 0..LEN as i64, &ChromaParams::default()).unwrap();
+    assert_eq!(dominant_class(&frames).0, 0, "C4");
+
+    // A4 tuned to 432 Hz is a third of a semitone below the analyzer's bands
+    let mut coefs = analyzed(&g, 432.0);
+    let params = ChromaParams { tuning_hz: 432.0, ..ChromaParams::default() };
+    let frames = chroma(&g, &mut coefs, 1024, 0..LEN as i64, &params).unwrap();
+    assert_eq!(dominant_class(&frames).0, 9);
+}
+
+#[test]
+fn chroma_needs_sample_rate() {
+    let g = Gaborator::new(&GaboratorParams::for_sample_rate(SAMPLE_RATE, 50.0, 440.0, 36));
+    let mut coefs = Coefs::new(&g);
+    let err = chroma(&g, &mut coefs, 1024, 0..LEN as i64, &ChromaParams::default()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
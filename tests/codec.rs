@@ -0,0 +1,86 @@
+use gaborator::codec::{snr_db, LossyParams};
+use gaborator::{Coefs, Gaborator, GaboratorParams};
+
+const LEN: usize = 20000;
+
+fn analyzer() -> Gaborator {
+    Gaborator::new(&GaboratorParams {
+        bands_per_octave: 48,
+        ff_min: 20.0 / 48000.0,
+        ff_ref: 440.0 / 48000.0,
+        overlap: 0.7,
+    })
+}
+
+fn signal() -> Vec<f32> {
+    (0..LEN)
+        .map(|i| (i as f32 * 440.0 / 48000.0 * std::f32::consts::TAU).sin() * 0.5)
+        .collect()
+}
+
+fn saved(g: &Gaborator) -> Vec<u8> {
+    let mut coefs = Coefs::new(g);
+    g.analyze(&signal(), 0, &mut coefs);
+    let mut bytes = Vec::new();
+    coefs.save_lossy(g, &mut bytes, &LossyParams::default()).unwrap();
+    bytes
+}
+
+#[test]
+fn lossy_roundtrip_keeps_sine() {
+    let g = analyzer();
+    let bytes = saved(&g);
+    let loaded = Coefs::load_lossy(&bytes[..], &g).unwrap();
+    let mut output = vec![0.0; LEN];
+    g.synthesize(&loaded, 0, &mut output);
+    let snr = snr_db(&signal(), &output);
+    assert!(snr > 45.0, "SNR {} dB", snr);
+    assert!(bytes.len() < LEN * 4, "{} bytes is larger than the samples", bytes.len());
+}
+
+#[test]
+fn truncated_lossy_file_is_rejected() {
+    let g = analyzer();
+    let bytes = saved(&g);
+    for len in [0, 8, 40, 60, bytes.len() / 2, bytes.len() - 1] {
+        assert!(Coefs::load_lossy(&bytes[..len], &g).is_err(), "prefix of {} bytes was accepted", len);
+    }
+}
+
+/// Offset of the length of the first run: after magic, version, parameters, settings, band maxima,
+/// number of runs, then band and time of the run
+fn first_len(g: &Gaborator) -> usize {
+    let n_bands = (g.band_lowpass() + 1) as usize;
+    8 + 4 + 28 + 4 + 2 + 4 + 4 * n_bands + 8 + 4 + 8
+}
+
+#[test]
+fn overlong_runs_are_rejected() {
+    let g = analyzer();
+    let first_len = first_len(&g);
+
+    let mut bytes = saved(&g);
+    bytes[first_len..first_len + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(Coefs::load_lossy(&bytes[..], &g).is_err(), "run longer than the limit");
+
+    let mut bytes = saved(&g);
+    bytes[first_len..first_len + 4].copy_from_slice(&(1u32 << 24).to_le_bytes());
+    assert!(Coefs::load_lossy(&bytes[..], &g).is_err(), "more coefficients than the payload holds");
+}
+
+#[test]
+fn invalid_runs_are_rejected() {
+    let g = analyzer();
+    let (band_offset, time_offset) = (first_len(&g) - 12, first_len(&g) - 8);
+    let corrupted = |offset: usize, data: &[u8]| {
+        let mut bytes = saved(&g);
+        bytes[offset..offset + data.len()].copy_from_slice(data);
+        Coefs::load_lossy(&bytes[..], &g).err().map(|e| e.kind())
+    };
+    let invalid = Some(std::io::ErrorKind::InvalidData);
+    assert_eq!(corrupted(band_offset, &(-1i32).to_le_bytes()), invalid, "negative band");
+    assert_eq!(corrupted(band_offset, &(g.band_lowpass() + 1).to_le_bytes()), invalid, "band past the lowpass band");
+    assert_eq!(corrupted(time_offset, &1i64.to_le_bytes()), invalid, "time alignment");
+    // An aligned time far from the other runs would make Gaborator index slices all the way to it
+    assert_eq!(corrupted(time_offset, &(1i64 << 52).to_le_bytes()), invalid, "runs far apart");
+}
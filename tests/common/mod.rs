@@ -0,0 +1,39 @@
+//! Helpers shared by tests of signal transformations
+
+use gaborator::{Gaborator, GaboratorParams};
+
+pub const SAMPLE_RATE: usize = 48000;
+
+/// Fine frequency resolution, with filters long enough for a steady partial's leakage into distant bands
+/// to rise and fall like a transient
+pub fn analyzer() -> Gaborator {
+    Gaborator::new(&GaboratorParams::for_sample_rate(SAMPLE_RATE as f64, 50.0, 440.0, 128))
+}
+
+/// 440 Hz sine with amplitude 0.5
+pub fn sine(len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| (i as f32 * 440.0 / SAMPLE_RATE as f32 * std::f32::consts::TAU).sin() * 0.5)
+        .collect()
+}
+
+/// Leave out 0.3 s at each end
+pub fn inner(signal: &[f32]) -> &[f32] {
+    let edge = 3 * SAMPLE_RATE / 10;
+    &signal[edge..signal.len() - edge]
+}
+
+/// Level of each 0.1 s chunk relative to the `sine`, in decibels
+pub fn chunk_levels_db(signal: &[f32]) -> Vec<f32> {
+    signal
+        .chunks_exact(SAMPLE_RATE / 10)
+        .map(|c| 10.0 * (c.iter().map(|x| x * x).sum::<f32>() / c.len() as f32 / 0.125).log10())
+        .collect()
+}
+
+/// Frequency in Hz from the number of upward zero crossings
+pub fn frequency_hz(signal: &[f32]) -> f64 {
+    let crossings: Vec<usize> = (1..signal.len()).filter(|i| signal[i - 1] < 0.0 && signal[*i] >= 0.0).collect();
+    let (first, last) = (crossings[0], crossings[crossings.len() - 1]);
+    (crossings.len() - 1) as f64 * SAMPLE_RATE as f64 / (last - first) as f64
+}
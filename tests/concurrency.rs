@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+const LEN: usize = 20000;
+
+fn params() -> gaborator::GaboratorParams {
+    gaborator::GaboratorParams {
+        bands_per_octave: 12,
+        ff_min: 0.01,
+        ff_ref: 440.0 / 48000.0,
+        overlap: 0.7,
+    }
+}
+
+fn signal(seed: usize) -> Vec<f32> {
+    let ff = 0.01 + 0.003 * seed as f32;
+    (0..LEN)
+        .map(|i| (i as f32 * ff * std::f32::consts::TAU).sin() * 0.5)
+        .collect()
+}
+
+/// Analyze, modify and resynthesize `signal` with a fresh coefficient set
+fn roundtrip(g: &gaborator::Gaborator, signal: &[f32]) -> Vec<f32> {
+    let mut coefs = gaborator::Coefs::new(g);
+    g.analyze(signal, 0, &mut coefs);
+    coefs.process(i32::MIN, i32::MAX, i64::MIN, i64::MAX, |_, c| {
+        c.re *= 0.5;
+        c.im *= 0.5;
+    });
+    let mut output = vec![0.0; signal.len()];
+    g.synthesize(&coefs, 0, &mut output);
+    output
+}
+
+#[test]
+fn shared_analyzer_gives_same_results_on_many_threads() {
+    let g: Arc<gaborator::Gaborator> = gaborator::GaboratorCache::get(&params());
+    let expected: Vec<Vec<f32>> = (0..8).map(|seed| roundtrip(&g, &signal(seed))).collect();
+
+    let threads: Vec<_> = (0..8)
+        .map(|seed| {
+            let g = gaborator::GaboratorCache::get(&params());
+            std::thread::spawn(move || {
+                let input = signal(seed);
+                (0..3).map(|_| roundtrip(&g, &input)).collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    for (seed, thread) in threads.into_iter().enumerate() {
+        for output in thread.join().unwrap() {
+            assert!(output == expected[seed], "thread {} got different output", seed);
+        }
+    }
+}
+
+#[test]
+fn analyzers_are_created_and_dropped_concurrently() {
+    let threads: Vec<_> = (0..4)
+        .map(|seed| {
+            std::thread::spawn(move || {
+                let input = signal(seed);
+                let outputs: Vec<_> = (0..2)
+                    .map(|_| roundtrip(&gaborator::Gaborator::new(&params()), &input))
+                    .collect();
+                outputs.windows(2).all(|w| w[0] == w[1])
+            })
+        })
+        .collect();
+    for thread in threads {
+        assert!(thread.join().unwrap());
+    }
+}
+
+#[test]
+fn cache_gives_one_analyzer_to_threads_racing_for_it() {
+    let params = gaborator::GaboratorParams { bands_per_octave: 13, ..params() };
+    let threads: Vec<_> = (0..4)
+        .map(|_| std::thread::spawn(move || gaborator::GaboratorCache::get(&params)))
+        .collect();
+    let analyzers: Vec<Arc<gaborator::Gaborator>> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+    for g in &analyzers {
+        assert!(Arc::ptr_eq(g, &analyzers[0]));
+    }
+    assert!(Arc::ptr_eq(&gaborator::GaboratorCache::get(&params), &analyzers[0]));
+}
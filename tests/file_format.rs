@@ -0,0 +1,106 @@
+use std::convert::TryInto;
+
+use gaborator::{Coefs, Gaborator, GaboratorParams};
+
+const LEN: usize = 10000;
+
+fn params() -> GaboratorParams {
+    GaboratorParams {
+        bands_per_octave: 12,
+        ff_min: 0.01,
+        ff_ref: 440.0 / 48000.0,
+        overlap: 0.7,
+    }
+}
+
+fn signal() -> Vec<f32> {
+    (0..LEN)
+        .map(|i| (i as f32 * 0.021 * std::f32::consts::TAU).sin() * 0.5)
+        .collect()
+}
+
+fn analyzed(g: &Gaborator) -> Coefs {
+    let mut coefs = Coefs::new(g);
+    g.analyze(&signal(), 0, &mut coefs);
+    coefs
+}
+
+fn saved(g: &Gaborator) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    analyzed(g).save(&mut bytes).unwrap();
+    bytes
+}
+
+fn snr_db(reference: &[f32], other: &[f32]) -> f64 {
+    let signal: f64 = reference.iter().map(|x| (*x as f64).powi(2)).sum();
+    let noise: f64 = reference.iter().zip(other).map(|(a, b)| (*a as f64 - *b as f64).powi(2)).sum();
+    10.0 * (signal / noise).log10()
+}
+
+#[test]
+fn saved_coefficients_load_back() {
+    let g = Gaborator::new(&params());
+    let mut coefs = analyzed(&g);
+    let mut bytes = Vec::new();
+    coefs.save(&mut bytes).unwrap();
+    let loaded = Coefs::load(&bytes[..], &g).unwrap();
+
+    let mut expected = vec![0.0; LEN];
+    g.synthesize(&coefs, 0, &mut expected);
+    let mut output = vec![0.0; LEN];
+    g.synthesize(&loaded, 0, &mut output);
+    assert_eq!(output, expected);
+    let snr = snr_db(&signal(), &output);
+    assert!(snr > 100.0, "SNR {} dB", snr);
+}
+
+#[test]
+fn truncated_file_is_rejected() {
+    let g = Gaborator::new(&params());
+    let bytes = saved(&g);
+    for len in [0, 7, 8, 20, 39, 40, 45, 60, bytes.len() / 2, bytes.len() - 1] {
+        assert!(Coefs::load(&bytes[..len], &g).is_err(), "prefix of {} bytes was accepted", len);
+    }
+}
+
+/// Offset of the first run record, after magic, version and parameters
+const FIRST_RUN: usize = 8 + 4 + 4 + 3 * 8;
+
+fn corrupted(g: &Gaborator, offset: usize, data: &[u8]) -> std::io::Result<Coefs> {
+    let mut bytes = saved(g);
+    bytes[offset..offset + data.len()].copy_from_slice(data);
+    Coefs::load(&bytes[..], g)
+}
+
+#[test]
+fn corrupt_file_is_rejected() {
+    let g = Gaborator::new(&params());
+    assert!(corrupted(&g, 0, b"NOTCOEFS").is_err(), "magic");
+    assert!(corrupted(&g, 8, &2u32.to_le_bytes()).is_err(), "version");
+    assert!(corrupted(&g, FIRST_RUN, &[7]).is_err(), "record tag");
+    assert!(corrupted(&g, FIRST_RUN + 1, &(-1000i32).to_le_bytes()).is_err(), "band");
+    assert!(corrupted(&g, FIRST_RUN + 5, &1i64.to_le_bytes()).is_err(), "time alignment");
+    assert!(corrupted(&g, FIRST_RUN + 13, &u32::MAX.to_le_bytes()).is_err(), "run length");
+    assert!(corrupted(&g, FIRST_RUN + 1, &i32::MAX.to_le_bytes()).is_err(), "band past the lowpass band");
+
+    let other = Gaborator::new(&GaboratorParams {
+        bands_per_octave: 24,
+        ..params()
+    });
+    let bytes = saved(&g);
+    assert!(Coefs::load(&bytes[..], &other).is_err(), "parameters");
+}
+
+#[test]
+fn runs_far_apart_are_rejected() {
+    let g = Gaborator::new(&params());
+    // An aligned time far from the other runs would make Gaborator index slices all the way to it
+    let bytes = saved(&g);
+    let band = i32::from_le_bytes(bytes[FIRST_RUN + 1..FIRST_RUN + 5].try_into().unwrap());
+    let step = g.band_time_step(band);
+    for sample_time in [1i64 << 52, -(1i64 << 52), (1i64 << 32) / step * step + step] {
+        let err = corrupted(&g, FIRST_RUN + 5, &sample_time.to_le_bytes()).err();
+        assert_eq!(err.map(|e| e.kind()), Some(std::io::ErrorKind::InvalidData), "time {}", sample_time);
+    }
+}
+
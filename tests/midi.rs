@@ -0,0 +1,75 @@
+use std::io::ErrorKind;
+
+use gaborator::midi::write_midi;
+use gaborator::notes::Note;
+use gaborator::transcribe::NoteEvent;
+
+/// 50 samples per tick at 120 beats per minute and 480 ticks per beat
+const SAMPLE_RATE: f64 = 48000.0;
+
+fn note(midi: i32, onset: i64, offset: i64, velocity: u8) -> NoteEvent {
+    NoteEvent { onset, offset, note: Note { midi, cents: 0.0 }, velocity }
+}
+
+/// Expected file: header chunk, then track chunk with the tempo event, given events and the end of track event
+fn midi_file(events: &[u8]) -> Vec<u8> {
+    let mut track = vec![0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20];
+    track.extend_from_slice(events);
+    track.extend_from_slice(&[0x00, 0xFF, 0x2F, 0x00]);
+
+    let mut file = b"MThd".to_vec();
+    file.extend_from_slice(&[0, 0, 0, 6, 0, 0, 0, 1, 0x01, 0xE0]);
+    file.extend_from_slice(b"MTrk");
+    file.extend_from_slice(&(track.len() as u32).to_be_bytes());
+    file.extend_from_slice(&track);
+    file
+}
+
+fn written(notes: &[NoteEvent]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_midi(&mut bytes, notes, SAMPLE_RATE).unwrap();
+    bytes
+}
+
+#[test]
+fn events_layout() {
+    let notes = [
+        note(60, 0, 48000, 100),
+        // Shorter than a tick, with velocity below the range
+        note(64, 24000, 24010, 0),
+        // Same key right after the first note
+        note(60, 48000, 96000, 127),
+    ];
+    #[rustfmt::skip]
+    let expected = midi_file(&[
+        0x00, 0x90, 60, 100,
+        0x83, 0x60, 0x90, 64, 1,
+        0x01, 0x80, 64, 64,
+        0x83, 0x5F, 0x80, 60, 64,
+        0x00, 0x90, 60, 127,
+        0x87, 0x40, 0x80, 60, 64,
+    ]);
+    assert_eq!(written(&notes), expected);
+}
+
+#[test]
+fn times_are_clamped_to_file_range() {
+    let notes = [note(69, -1000, 100, 64), note(70, i64::MAX / 4, i64::MAX / 2, 64)];
+    #[rustfmt::skip]
+    let expected = midi_file(&[
+        0x00, 0x90, 69, 64,
+        0x02, 0x80, 69, 64,
+        // The largest time a 4-byte delta can reach, minus a tick for the note to last
+        0xFF, 0xFF, 0xFF, 0x7C, 0x90, 70, 64,
+        0x01, 0x80, 70, 64,
+    ]);
+    assert_eq!(written(&notes), expected);
+}
+
+#[test]
+fn notes_out_of_range_are_rejected() {
+    for midi in [-1, 128] {
+        let err = write_midi(Vec::new(), &[note(midi, 0, 1000, 64)], SAMPLE_RATE).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,79 @@
+use gaborator::notes::{note_name, parse_note_name, Note};
+use gaborator::{Gaborator, GaboratorParams};
+
+const SAMPLE_RATE: f64 = 48000.0;
+
+#[test]
+fn note_names_parse() {
+    assert_eq!(parse_note_name("A4"), Some(69));
+    assert_eq!(parse_note_name("C4"), Some(60));
+    assert_eq!(parse_note_name("a4"), Some(69));
+    assert_eq!(parse_note_name("C-1"), Some(0));
+    assert_eq!(parse_note_name("G9"), Some(127));
+    assert_eq!(parse_note_name("A10"), Some(141));
+}
+
+#[test]
+fn sharps_and_flats_parse() {
+    assert_eq!(parse_note_name("C#3"), Some(49));
+    assert_eq!(parse_note_name("Db3"), Some(49));
+    assert_eq!(parse_note_name("Bb-1"), Some(10));
+    assert_eq!(parse_note_name("bb3"), Some(58));
+    // Accidentals may cross octave boundaries, which are between B and C
+    assert_eq!(parse_note_name("Cb4"), Some(59));
+    assert_eq!(parse_note_name("B#3"), Some(60));
+    assert_eq!(parse_note_name("E#4"), parse_note_name("F4"));
+    assert_eq!(parse_note_name("Fb4"), parse_note_name("E4"));
+}
+
+#[test]
+fn invalid_note_names_are_rejected() {
+    for name in ["", "4", "H4", "A", "A#", "Ab", "A##4", "Abb4", "Ax4", "A 4", "A4 ", "A+4", "A4.5", "#4", "A99999999999"] {
+        assert_eq!(parse_note_name(name), None, "{:?}", name);
+    }
+}
+
+#[test]
+fn note_names_roundtrip() {
+    for midi in -24..160 {
+        let name = note_name(midi);
+        assert_eq!(parse_note_name(&name), Some(midi), "{}", name);
+    }
+    assert_eq!(note_name(69), "A4");
+    assert_eq!(note_name(61), "C#4");
+    assert_eq!(note_name(0), "C-1");
+    assert_eq!(note_name(-1), "B-2");
+    assert_eq!(Note { midi: 70, cents: -30.0 }.name(), "A#4");
+}
+
+#[test]
+fn notes_of_frequencies() {
+    let note = Note::from_hz(440.0 * (0.25f64 / 12.0).exp2());
+    assert_eq!(note.midi, 69);
+    assert!((note.cents - 25.0).abs() < 1e-9, "cents {}", note.cents);
+    assert!((note.hz() - 440.0 * (0.25f64 / 12.0).exp2()).abs() < 1e-9);
+    assert!((Note { midi: 60, cents: 0.0 }.hz() - 261.6256).abs() < 1e-4);
+}
+
+#[test]
+fn band_alignment_to_notes() {
+    let aligned = |bands_per_octave, ff_ref_hz| {
+        let params = GaboratorParams::for_sample_rate(SAMPLE_RATE, 50.0, ff_ref_hz, bands_per_octave);
+        Gaborator::with_sample_rate(&params, SAMPLE_RATE).bands_are_note_aligned()
+    };
+    assert_eq!(aligned(12, 440.0), Some(true));
+    assert_eq!(aligned(48, 440.0), Some(true));
+    assert_eq!(aligned(36, 261.6256), Some(true), "reference at C4");
+    assert_eq!(aligned(24, 445.0), Some(false), "reference 19.6 cents sharp of A4");
+    assert_eq!(aligned(10, 440.0), Some(false), "bands do not divide octave into semitones");
+    assert_eq!(aligned(18, 440.0), Some(false), "every other band falls between semitones");
+
+    let params = GaboratorParams::for_sample_rate(SAMPLE_RATE, 50.0, 440.0, 24);
+    let g = Gaborator::with_sample_rate(&params, SAMPLE_RATE);
+    for midi in 40..100 {
+        let band = g.band_for_midi(midi).unwrap();
+        let note = g.band_note(band).unwrap();
+        assert_eq!(note.midi, midi);
+        assert!(note.cents.abs() < 1e-6, "note {} is off by {} cents", midi, note.cents);
+    }
+}
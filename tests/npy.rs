@@ -0,0 +1,138 @@
+use std::convert::TryInto;
+
+use gaborator::{Coef, Gaborator, GaboratorParams, Grid};
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// Bitwise CRC-32 as used by zip, independent of the table-driven one being tested
+fn crc32(data: &[u8]) -> u32 {
+    let mut c = !0u32;
+    for b in data {
+        c ^= *b as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+        }
+    }
+    !c
+}
+
+/// Expected `.npy` header: magic, version 1.0, header length, then the dict padded with spaces
+/// and ended with a newline so that data starts at byte 128
+fn npy_header(dict: &str) -> Vec<u8> {
+    let mut header = b"\x93NUMPY\x01\x00".to_vec();
+    header.extend_from_slice(&118u16.to_le_bytes());
+    header.extend_from_slice(dict.as_bytes());
+    header.resize(127, b' ');
+    header.push(b'\n');
+    header
+}
+
+fn grid() -> Grid<Coef> {
+    Grid {
+        band_begin: 5,
+        n_bands: 2,
+        time_begin: -64,
+        hop: 32,
+        n_frames: 3,
+        data: (0..6).map(|i| Coef::new(i as f32, -(i as f32))).collect(),
+    }
+}
+
+#[test]
+fn one_dimensional_npy_layout() {
+    let mut bytes = Vec::new();
+    gaborator::write_npy(&mut bytes, &[1.5f32, -2.0]).unwrap();
+
+    let mut expected = npy_header("{'descr': '<f4', 'fortran_order': False, 'shape': (2,), }");
+    assert_eq!(expected.len() % 64, 0);
+    expected.extend_from_slice(&1.5f32.to_le_bytes());
+    expected.extend_from_slice(&(-2.0f32).to_le_bytes());
+    assert_eq!(bytes, expected);
+}
+
+#[test]
+fn grid_npy_layout() {
+    let mut bytes = Vec::new();
+    grid().write_npy(&mut bytes).unwrap();
+
+    let mut expected = npy_header("{'descr': '<c8', 'fortran_order': False, 'shape': (2, 3), }");
+    for i in 0..6 {
+        expected.extend_from_slice(&(i as f32).to_le_bytes());
+        expected.extend_from_slice(&(-(i as f32)).to_le_bytes());
+    }
+    assert_eq!(bytes, expected);
+}
+
+#[test]
+fn npz_zip_records() {
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    let g = Gaborator::new(&GaboratorParams {
+        bands_per_octave: 12,
+        ff_min: 0.01,
+        ff_ref: 0.125,
+        overlap: 0.7,
+    });
+    let grid = grid();
+    let mut bytes = Vec::new();
+    grid.write_npz(&g, &mut bytes).unwrap();
+
+    let mut values = Vec::new();
+    grid.write_npy(&mut values).unwrap();
+    let mut band = Vec::new();
+    gaborator::write_npy(&mut band, &grid.band_axis()).unwrap();
+    let mut band_ff = Vec::new();
+    gaborator::write_npy(&mut band_ff, &grid.band_ff_axis(&g)).unwrap();
+    let mut time = Vec::new();
+    gaborator::write_npy(&mut time, &grid.time_axis()).unwrap();
+    let members = [("values.npy", values), ("band.npy", band), ("band_ff.npy", band_ff), ("time.npy", time)];
+
+    // Local file headers, each followed by the stored member
+    let mut offset = 0;
+    let mut local_offsets = Vec::new();
+    for (name, data) in &members {
+        local_offsets.push(offset as u32);
+        let local = &bytes[offset..];
+        assert_eq!(u32_at(local, 0), 0x0403_4b50, "{} signature", name);
+        assert_eq!(u16_at(local, 4), 20, "{} version needed", name);
+        assert_eq!(u16_at(local, 6), 0, "{} flags", name);
+        assert_eq!(u16_at(local, 8), 0, "{} method", name);
+        assert_eq!(u32_at(local, 14), crc32(data), "{} crc", name);
+        assert_eq!(u32_at(local, 18), data.len() as u32, "{} compressed size", name);
+        assert_eq!(u32_at(local, 22), data.len() as u32, "{} size", name);
+        assert_eq!(u16_at(local, 26) as usize, name.len());
+        assert_eq!(u16_at(local, 28), 0, "{} extra field", name);
+        assert_eq!(&local[30..30 + name.len()], name.as_bytes());
+        assert_eq!(&local[30 + name.len()..30 + name.len() + data.len()], &data[..], "{} data", name);
+        offset += 30 + name.len() + data.len();
+    }
+
+    // Central directory entries repeat the local headers and point to them
+    let central_begin = offset;
+    for ((name, _), local_offset) in members.iter().zip(local_offsets) {
+        let entry = &bytes[offset..];
+        let local = &bytes[local_offset as usize..];
+        assert_eq!(u32_at(entry, 0), 0x0201_4b50, "{} signature", name);
+        assert_eq!(u16_at(entry, 4), 20, "{} version made by", name);
+        assert_eq!(&entry[6..32], &local[4..30], "{} fields shared with the local header", name);
+        assert_eq!(&entry[32..42], &[0; 10], "{} comment, disk and attributes", name);
+        assert_eq!(u32_at(entry, 42), local_offset, "{} local header offset", name);
+        assert_eq!(&entry[46..46 + name.len()], name.as_bytes());
+        offset += 46 + name.len();
+    }
+
+    let end = &bytes[offset..];
+    assert_eq!(end.len(), 22, "end of central directory record ends the archive");
+    assert_eq!(u32_at(end, 0), 0x0605_4b50);
+    assert_eq!(u32_at(end, 4), 0, "disk numbers");
+    assert_eq!(u16_at(end, 8), 4, "entries on this disk");
+    assert_eq!(u16_at(end, 10), 4, "entries");
+    assert_eq!(u32_at(end, 12) as usize, offset - central_begin, "central directory size");
+    assert_eq!(u32_at(end, 16) as usize, central_begin, "central directory offset");
+    assert_eq!(u16_at(end, 20), 0, "comment length");
+}
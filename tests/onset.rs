@@ -0,0 +1,53 @@
+use std::io::ErrorKind;
+
+use gaborator::onset::{detect_onsets, NoveltyMethod, OnsetParams};
+use gaborator::{Coefs, Gaborator, GaboratorParams};
+
+const SAMPLE_RATE: f64 = 48000.0;
+const LEN: usize = 2 * SAMPLE_RATE as usize;
+
+fn analyzer() -> Gaborator {
+    Gaborator::with_sample_rate(&GaboratorParams::for_sample_rate(SAMPLE_RATE, 30.0, 440.0, 12), SAMPLE_RATE)
+}
+
+/// Irregularly spaced click times, in samples
+const CLICKS: [usize; 6] = [4800, 16800, 24000, 38400, 57600, 80000];
+
+/// Short decaying noise bursts at `CLICKS`, on top of a steady tone
+fn clicks() -> Vec<f32> {
+    let mut noise = 12345u32;
+    let mut signal: Vec<f32> = (0..LEN)
+        .map(|i| (i as f64 * 220.0 / SAMPLE_RATE * std::f64::consts::TAU).sin() as f32 * 0.1)
+        .collect();
+    for click in CLICKS {
+        for (i, x) in signal[click..click + 480].iter_mut().enumerate() {
+            // Linear congruential generator, for noise independent of external crates
+            noise = noise.wrapping_mul(1664525).wrapping_add(1013904223);
+            *x += ((noise >> 8) as f32 / (1 << 24) as f32 - 0.5) * (-(i as f32) / 96.0).exp();
+        }
+    }
+    signal
+}
+
+#[test]
+fn onsets_are_detected_at_clicks() {
+    let g = analyzer();
+    let mut coefs = Coefs::new(&g);
+    g.analyze(&clicks(), 0, &mut coefs);
+    for method in [NoveltyMethod::SpectralFlux, NoveltyMethod::ComplexDomain] {
+        let params = OnsetParams { method, ..OnsetParams::default() };
+        let onsets = detect_onsets(&g, &mut coefs, 0..LEN as i64, &params).unwrap();
+        assert_eq!(onsets.len(), CLICKS.len(), "{:?}: {:?}", method, onsets);
+        for (onset, click) in onsets.iter().zip(CLICKS) {
+            assert!((onset - click as i64).abs() <= params.hop as i64, "{:?}: {:?}", method, onsets);
+        }
+    }
+}
+
+#[test]
+fn onsets_need_sample_rate() {
+    let g = Gaborator::new(&GaboratorParams::for_sample_rate(SAMPLE_RATE, 30.0, 440.0, 12));
+    let mut coefs = Coefs::new(&g);
+    let err = detect_onsets(&g, &mut coefs, 0..LEN as i64, &OnsetParams::default()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
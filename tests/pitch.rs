@@ -0,0 +1,97 @@
+use gaborator::pitch::{track_pitch, PitchFrame, PitchParams};
+use gaborator::{Coefs, Gaborator, GaboratorParams};
+
+const SAMPLE_RATE: f64 = 48000.0;
+const LEN: usize = SAMPLE_RATE as usize;
+
+/// Bands a semitone apart, centered on notes
+fn analyzer() -> Gaborator {
+    Gaborator::with_sample_rate(&GaboratorParams::for_sample_rate(SAMPLE_RATE, 40.0, 440.0, 12), SAMPLE_RATE)
+}
+
+/// Sum of harmonics of `f0` with given amplitudes, the first being the fundamental
+fn harmonic_tone(f0: f64, amplitudes: &[f32]) -> Vec<f32> {
+    (0..LEN)
+        .map(|i| {
+            let phase = i as f64 * f0 / SAMPLE_RATE * std::f64::consts::TAU;
+            amplitudes.iter().enumerate().map(|(h, a)| a * (phase * (h + 1) as f64).sin() as f32).sum()
+        })
+        .collect()
+}
+
+/// Mostly even harmonics: the fundamental is much weaker than the 2nd harmonic,
+/// and the harmonic sum of the octave above is a bit stronger than that of the fundamental
+const WEAK_FUNDAMENTAL: [f32; 6] = [0.15, 0.5, 0.05, 0.3, 0.05, 0.2];
+
+fn tracked(g: &Gaborator, signal: &[f32], params: &PitchParams) -> Vec<PitchFrame> {
+    let mut coefs = Coefs::new(g);
+    g.analyze(signal, 0, &mut coefs);
+    track_pitch(g, &mut coefs, 0..signal.len() as i64, params).unwrap()
+}
+
+/// Deviation of each voiced frame from `hz`, in cents, within the given time range
+fn cents_from(frames: &[PitchFrame], hz: f64, from_sample_time: i64, to_sample_time: i64) -> Vec<f64> {
+    frames
+        .iter()
+        .filter(|f| f.sample_time >= from_sample_time && f.sample_time < to_sample_time)
+        .map(|f| {
+            assert!(f.voiced, "frame at {} is unvoiced", f.sample_time);
+            1200.0 * (f.hz / hz).log2()
+        })
+        .collect()
+}
+
+fn assert_near(cents: &[f64], tolerance: f64) {
+    assert!(!cents.is_empty());
+    for c in cents {
+        assert!(c.abs() < tolerance, "{} cents off in {:?}", c, cents);
+    }
+}
+
+#[test]
+fn frequency_between_bands_is_interpolated() {
+    let g = analyzer();
+    let quarter = LEN as i64 / 4;
+    for offset_cents in [-40.0, -20.0, 0.0, 20.0, 40.0] {
+        let f0 = 220.0 * (offset_cents / 1200.0f64).exp2();
+        let frames = tracked(&g, &harmonic_tone(f0, &[0.5, 0.3, 0.2, 0.1]), &PitchParams::default());
+        // Well within the 100 cent band spacing
+        assert_near(&cents_from(&frames, f0, quarter, 3 * quarter), 15.0);
+    }
+}
+
+#[test]
+fn weak_fundamental_is_preferred_over_octave_above() {
+    let g = analyzer();
+    let f0 = 220.0 * (0.3f64 / 12.0).exp2();
+    let signal = harmonic_tone(f0, &WEAK_FUNDAMENTAL);
+    let quarter = LEN as i64 / 4;
+    let params = PitchParams { octave_window: 0, ..PitchParams::default() };
+    assert_near(&cents_from(&tracked(&g, &signal, &params), f0, quarter, 3 * quarter), 15.0);
+
+    // Without the correction the octave above wins
+    let params = PitchParams { octave_tolerance: 2.0, ..params };
+    assert_near(&cents_from(&tracked(&g, &signal, &params), 2.0 * f0, quarter, 3 * quarter), 15.0);
+}
+
+#[test]
+fn brief_octave_jump_is_corrected_from_neighbours() {
+    let g = analyzer();
+    let f0 = 220.0 * (0.3f64 / 12.0).exp2();
+    let strong = harmonic_tone(f0, &[0.5, 0.3, 0.2, 0.1]);
+    // Fundamental too weak for the lower octave preference in the middle
+    let mut weak = WEAK_FUNDAMENTAL;
+    weak[0] = 0.1;
+    let weak = harmonic_tone(f0, &weak);
+    let jump = LEN / 2..LEN / 2 + 8192;
+    let signal: Vec<f32> = (0..LEN).map(|i| if jump.contains(&i) { weak[i] } else { strong[i] }).collect();
+    let (from, to) = (jump.start as i64 - 4096, jump.end as i64 + 4096);
+
+    let params = PitchParams { octave_window: 0, ..PitchParams::default() };
+    let frames = tracked(&g, &signal, &params);
+    let jumped = cents_from(&frames, f0, from, to).iter().filter(|c| (*c - 1200.0).abs() < 15.0).count();
+    assert!(jumped > 10, "only {} frames an octave up", jumped);
+
+    let params = PitchParams { octave_window: 20, ..PitchParams::default() };
+    assert_near(&cents_from(&tracked(&g, &signal, &params), f0, from, to), 15.0);
+}
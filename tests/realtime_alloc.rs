@@ -0,0 +1,188 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts allocations made on threads that have enabled counting.
+///
+/// Allocations through Rust's global allocator include Gaborator's bulk memory
+/// (`pod_vector`s, coefficient slices and temporary buffers, see `gaborator-sys/patches/pod_alloc.patch`).
+/// Its other allocations, such as growth of the `std::vector` indexing coefficient slices,
+/// use C++ `operator new`, which is counted by replacing it below.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn count_allocation() {
+    if COUNTING.with(|c| c.get()) {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Replacements of C++ `operator new`, plain and array versions, by their Itanium C++ ABI names.
+/// The default `operator delete` releases memory using `free`, so allocating with `malloc` matches it.
+#[cfg(not(target_env = "msvc"))]
+#[allow(non_snake_case)]
+mod cxx_operator_new {
+    extern "C" {
+        fn malloc(size: usize) -> *mut u8;
+    }
+
+    #[no_mangle]
+    pub extern "C" fn _Znwm(size: usize) -> *mut u8 {
+        super::count_allocation();
+        let p = unsafe { malloc(size.max(1)) };
+        if p.is_null() {
+            // Throwing `std::bad_alloc` is not possible from here
+            std::process::abort();
+        }
+        p
+    }
+
+    #[no_mangle]
+    pub extern "C" fn _Znam(size: usize) -> *mut u8 {
+        _Znwm(size)
+    }
+}
+
+fn count_allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    COUNTING.with(|c| c.set(true));
+    f();
+    COUNTING.with(|c| c.set(false));
+    ALLOCATIONS.load(Ordering::SeqCst) - before
+}
+
+struct Gain(f32);
+
+impl gaborator::SpectralEffect for Gain {
+    fn process(&mut self, _meta: gaborator::SliceMeta, coefs: &mut [gaborator::Coef]) {
+        for c in coefs {
+            c.re *= self.0;
+            c.im *= self.0;
+        }
+    }
+}
+
+const BLOCK: usize = 256;
+
+fn analyzer() -> gaborator::Gaborator {
+    gaborator::Gaborator::new(&gaborator::GaboratorParams {
+        bands_per_octave: 12,
+        ff_min: 0.02,
+        ff_ref: 440.0 / 48000.0,
+        overlap: 0.7,
+    })
+}
+
+fn signal(block_index: usize) -> Vec<f32> {
+    (0..BLOCK)
+        .map(|i| ((block_index * BLOCK + i) as f32 * 0.03).sin() * 0.5)
+        .collect()
+}
+
+#[test]
+fn realtime_processor_does_not_allocate_in_steady_state() {
+    let g = analyzer();
+    let mut processor = gaborator::StreamingProcessor::new_realtime(&g, 0, BLOCK);
+    let mut effect = Gain(0.5);
+    let mut output = vec![0.0; BLOCK];
+
+    // Let the coefficient storage reach its working size
+    let steady_after = 2 * processor.latency() / BLOCK + 4;
+    for i in 0..steady_after {
+        processor.process_block_with_effect(&signal(i), &mut output, &mut effect).unwrap();
+    }
+
+    let inputs: Vec<Vec<f32>> = (steady_after..steady_after + 20).map(signal).collect();
+    let mut peak = 0.0f32;
+    let n = count_allocations(|| {
+        for input in &inputs {
+            processor.process_block_with_effect(input, &mut output, &mut effect).unwrap();
+            processor.process_block(input, &mut output, |_meta, c| {
+                c.re *= 2.0;
+                c.im *= 2.0;
+            });
+            peak = output.iter().fold(peak, |p, x| p.max(x.abs()));
+        }
+    });
+    assert_eq!(n, 0, "allocations in steady state");
+    assert!(peak > 0.1, "processed signal should pass through");
+}
+
+/// Runs another real-time processor on slices of the lowpass band, as an effect that processes a side chain could
+struct Nested<'a> {
+    inner: gaborator::StreamingProcessor<'a>,
+    lowpass_band: i32,
+    input: Vec<f32>,
+    output: Vec<f32>,
+    inner_blocks: usize,
+}
+
+impl gaborator::SpectralEffect for Nested<'_> {
+    fn process(&mut self, meta: gaborator::SliceMeta, _coefs: &mut [gaborator::Coef]) {
+        if meta.band == self.lowpass_band {
+            self.inner.process_block_with_effect(&self.input, &mut self.output, &mut Gain(0.5)).unwrap();
+            self.inner_blocks += 1;
+        }
+    }
+}
+
+#[test]
+fn nested_realtime_processors_do_not_allocate_in_steady_state() {
+    let g = analyzer();
+    let mut outer = gaborator::StreamingProcessor::new_realtime(&g, 0, BLOCK);
+    let mut effect = Nested {
+        inner: gaborator::StreamingProcessor::new_realtime(&g, 0, BLOCK),
+        lowpass_band: g.band_lowpass(),
+        input: signal(0),
+        output: vec![0.0; BLOCK],
+        inner_blocks: 0,
+    };
+    let mut output = vec![0.0; BLOCK];
+
+    let steady_after = 2 * outer.latency() / BLOCK + 4;
+    for i in 0..steady_after {
+        outer.process_block_with_effect(&signal(i), &mut output, &mut effect).unwrap();
+    }
+
+    let inputs: Vec<Vec<f32>> = (steady_after..steady_after + 20).map(signal).collect();
+    let inner_blocks = effect.inner_blocks;
+    let n = count_allocations(|| {
+        for input in &inputs {
+            outer.process_block_with_effect(input, &mut output, &mut effect).unwrap();
+        }
+    });
+    assert!(effect.inner_blocks > inner_blocks, "inner processor should run");
+    assert_eq!(n, 0, "allocations in steady state");
+}
+
+#[test]
+fn non_realtime_processor_allocates() {
+    let g = analyzer();
+    let mut processor = gaborator::StreamingProcessor::new(&g, 0);
+    let mut output = vec![0.0; BLOCK];
+    let input = signal(0);
+    let n = count_allocations(|| processor.process_block(&input, &mut output, |_, _| ()));
+    assert!(n > 0, "counting allocator should see Gaborator's allocations");
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "serde")]
+
+use gaborator::Grid;
+
+#[test]
+fn grid_roundtrips_through_json() {
+    let grid = Grid {
+        band_begin: 3,
+        n_bands: 2,
+        time_begin: -100,
+        hop: 64,
+        n_frames: 3,
+        data: vec![1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0],
+    };
+    let json = serde_json::to_string(&grid).unwrap();
+    let back: Grid<f32> = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, grid);
+}
+
+#[test]
+fn grid_with_wrong_data_length_is_rejected() {
+    let json = r#"{"band_begin":3,"n_bands":2,"time_begin":0,"hop":64,"n_frames":3,"data":[1.0,2.0]}"#;
+    let err = serde_json::from_str::<Grid<f32>>(json).unwrap_err();
+    assert!(err.to_string().contains("2 bands and 3 frames"), "{}", err);
+
+    let json = r#"{"band_begin":0,"n_bands":18446744073709551615,"time_begin":0,"hop":1,"n_frames":2,"data":[]}"#;
+    assert!(serde_json::from_str::<Grid<f32>>(json).is_err());
+}
+
+#[test]
+fn params_roundtrip_through_json() {
+    let params = gaborator::GaboratorParams {
+        bands_per_octave: 24,
+        ff_min: 0.0625,
+        ff_ref: 0.125,
+        overlap: 0.75,
+    };
+    let json = serde_json::to_string(&params).unwrap();
+    let back: gaborator::GaboratorParams = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, params);
+}
+
+#[test]
+fn csv_header_roundtrips_through_json() {
+    use gaborator::csv::{Columns, Header};
+    for columns in [Columns::Polar, Columns::Cartesian] {
+        let header = Header {
+            params: gaborator::GaboratorParams {
+                bands_per_octave: 48,
+                ff_min: 0.0009765625,
+                ff_ref: 0.125,
+                overlap: 0.7,
+            },
+            sample_rate: 48000,
+            columns,
+        };
+        let json = serde_json::to_string(&header).unwrap();
+        let back: Header = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, header);
+    }
+    assert_eq!(serde_json::to_string(&Columns::Polar).unwrap(), r#""Polar""#);
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::{analyzer, chunk_levels_db, frequency_hz, inner, sine, SAMPLE_RATE};
+use gaborator::shift::{pitch_shift_signal, ShiftParams};
+
+/// Spectral envelope of the harmonic source: a formant an octave wide around 1 kHz, over a floor
+fn formant(hz: f64) -> f64 {
+    let octaves = (hz / 1000.0).log2();
+    (-0.5 * (octaves / 0.5).powi(2)).exp() + 0.05
+}
+
+/// Harmonics of 200 Hz up to 4 kHz with amplitudes following `formant`, scaled by 0.1
+fn harmonic_source(len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| {
+            let phase = i as f64 * 200.0 / SAMPLE_RATE as f64 * std::f64::consts::TAU;
+            (1..=20).map(|h| formant(h as f64 * 200.0) * (phase * h as f64).sin()).sum::<f64>() as f32 * 0.1
+        })
+        .collect()
+}
+
+/// Amplitude of the sinusoid of frequency `hz` within `signal`, by correlation with it
+fn amplitude(signal: &[f32], hz: f64) -> f64 {
+    let w = hz / SAMPLE_RATE as f64 * std::f64::consts::TAU;
+    let (re, im) = signal.iter().enumerate().fold((0.0, 0.0), |(re, im), (n, x)| {
+        (re + *x as f64 * (w * n as f64).cos(), im - *x as f64 * (w * n as f64).sin())
+    });
+    2.0 * re.hypot(im) / signal.len() as f64
+}
+
+#[test]
+fn shifting_steady_sine_keeps_level_and_transposes() {
+    let g = analyzer();
+    let input = sine(2 * SAMPLE_RATE);
+    for semitones in [12.0, -12.0, 3.0] {
+        let output = pitch_shift_signal(&g, &input, semitones, &ShiftParams::default());
+        assert_eq!(output.len(), input.len());
+        for (i, db) in chunk_levels_db(inner(&output)).into_iter().enumerate() {
+            assert!(db.abs() < 1.0, "{} semitones: chunk {} is {} dB off", semitones, i + 3, db);
+        }
+        let expected = 440.0 * 2f64.powf(semitones / 12.0);
+        let hz = frequency_hz(inner(&output));
+        assert!((hz / expected - 1.0).abs() < 0.002, "{} semitones: {} Hz instead of {}", semitones, hz, expected);
+    }
+}
+
+#[test]
+fn formants_stay_while_harmonics_move() {
+    let g = analyzer();
+    let input = harmonic_source(2 * SAMPLE_RATE);
+    let semitones = 4.0;
+    let ratio = 2f64.powf(semitones / 12.0);
+    for preserve_formants in [true, false] {
+        let params = ShiftParams { preserve_formants, ..ShiftParams::default() };
+        let output = pitch_shift_signal(&g, &input, semitones, &params);
+        let output = inner(&output);
+        for h in 1..=14 {
+            let hz = h as f64 * 200.0;
+            // Harmonics move to the transposed frequencies
+            let moved = amplitude(output, hz * ratio) / 0.1;
+            assert!(amplitude(output, hz) / 0.1 < 0.03 * moved, "harmonic {} stays at {} Hz", h, hz);
+
+            // Either following the envelope at their new frequencies, or carrying their levels along
+            let expected = if preserve_formants { formant(hz * ratio) } else { formant(hz) };
+            let db = 20.0 * (moved / expected).log10();
+            assert!(db.abs() < 2.5, "preserve formants {}: harmonic {} is {} dB off", preserve_formants, h, db);
+        }
+    }
+}
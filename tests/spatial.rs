@@ -0,0 +1,108 @@
+use std::io::ErrorKind;
+
+use gaborator::spatial::Panner;
+use gaborator::{Chain, Coefs, Gaborator, GaboratorParams, MultiCoefs, SpectralEffect, StreamingProcessor};
+
+fn analyzer() -> Gaborator {
+    Gaborator::new(&GaboratorParams { bands_per_octave: 12, ff_min: 0.01, ff_ref: 1.0, overlap: 0.7 })
+}
+
+/// Interleaved stereo sine, centered
+fn centered(n_frames: usize) -> Vec<f32> {
+    (0..n_frames)
+        .flat_map(|i| {
+            let x = (i as f32 * 0.1).sin() * 0.5;
+            [x, x]
+        })
+        .collect()
+}
+
+fn rms(frames: &[f32], channel: usize) -> f32 {
+    let samples: Vec<f32> = frames.iter().skip(channel).step_by(2).copied().collect();
+    (samples.iter().map(|x| x * x).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+#[test]
+fn panner_in_chain_moves_centered_signal_left() {
+    let g = analyzer();
+    let n_frames = 8192;
+    let input = centered(n_frames);
+    let mut coefs = MultiCoefs::new(&g, 2);
+    g.analyze_interleaved(&input, 0, &mut coefs);
+
+    let mut chain = Chain::new().with(Panner::new(|_, _, _| -1.0));
+    chain.prepare(&g);
+    coefs.apply_effect(&mut chain).unwrap();
+
+    let mut output = vec![0.0; input.len()];
+    g.synthesize_interleaved(&coefs, 0, &mut output);
+    let middle = &output[n_frames / 2..n_frames * 3 / 2];
+    // All energy of both channels goes to the left one
+    assert!((rms(middle, 0) - rms(&input, 0) * 2f32.sqrt()).abs() < 0.01, "left rms {}", rms(middle, 0));
+    assert!(rms(middle, 1) < 0.001, "right rms {}", rms(middle, 1));
+}
+
+#[test]
+fn panner_in_stereo_streaming_processor_matches_offline() {
+    let g = analyzer();
+    let block = 512;
+    let n_frames = 48 * block;
+    let input = centered(n_frames);
+
+    let mut offline_coefs = MultiCoefs::new(&g, 2);
+    g.analyze_interleaved(&input, 0, &mut offline_coefs);
+    let mut offline_panner = Panner::new(|ff: f64, _, _: &_| if ff > 0.015 { 0.5 } else { -0.5 });
+    offline_panner.prepare(&g);
+    offline_panner.apply(&mut offline_coefs, i64::MIN, i64::MAX).unwrap();
+    let mut offline = vec![0.0; input.len()];
+    g.synthesize_interleaved(&offline_coefs, 0, &mut offline);
+
+    let mut processor = StreamingProcessor::new_multichannel(&g, 0, 2);
+    let latency = processor.latency();
+    assert!(3 * latency < n_frames, "latency {} leaves nothing to compare", latency);
+    let mut panner = Panner::new(|ff: f64, _, _: &_| if ff > 0.015 { 0.5 } else { -0.5 });
+    panner.prepare(&g);
+    let mut streamed = Vec::new();
+    let mut output = vec![0.0; 2 * block];
+    for chunk in input.chunks(2 * block) {
+        processor.process_interleaved_with_effect(chunk, &mut output, &mut panner).unwrap();
+        streamed.extend_from_slice(&output);
+    }
+
+    // Streamed output lags by `latency` frames; compare where both are complete
+    for t in latency..n_frames - latency {
+        for c in 0..2 {
+            let (s, o) = (streamed[2 * (t + latency) + c], offline[2 * t + c]);
+            assert!((s - o).abs() < 1e-3, "frame {} channel {}: streamed {} offline {}", t, c, s, o);
+        }
+    }
+}
+
+#[test]
+fn panner_rejects_other_channel_counts() {
+    let g = analyzer();
+    let mono: Vec<f32> = centered(4096).into_iter().step_by(2).collect();
+    let mut panner = Panner::new(|_, _, _: &_| -1.0);
+    panner.prepare(&g);
+
+    let mut coefs = Coefs::new(&g);
+    g.analyze(&mono, 0, &mut coefs);
+    let err = coefs.apply_effect(&mut panner).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let mut output = vec![0.0; mono.len()];
+    g.synthesize(&coefs, 0, &mut output);
+    assert!(output.iter().zip(&mono).all(|(o, i)| (o - i).abs() < 1e-3), "mono coefficients were modified");
+
+    let mut chain = Chain::new().with(Panner::new(|_, _, _: &_| -1.0));
+    chain.prepare(&g);
+    for n_channels in [1, 3] {
+        let mut coefs = MultiCoefs::new(&g, n_channels);
+        let err = coefs.apply_effect(&mut chain).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput, "{} channels", n_channels);
+    }
+
+    let mut processor = StreamingProcessor::new(&g, 0);
+    let mut output = vec![0.0; 512];
+    let err = processor.process_block_with_effect(&mono[..512], &mut output, &mut panner).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use common::{analyzer, chunk_levels_db, frequency_hz, inner, sine, SAMPLE_RATE};
+use gaborator::stretch::{time_stretch_signal, time_stretch_with, StretchParams};
+use gaborator::Coefs;
+
+/// Single-sample clicks every 0.25 s, and their sample times
+fn click_train(len: usize) -> (Vec<f32>, Vec<usize>) {
+    let clicks: Vec<usize> = (SAMPLE_RATE / 4..len - SAMPLE_RATE / 4).step_by(SAMPLE_RATE / 4).collect();
+    let mut signal = vec![0.0; len];
+    for click in &clicks {
+        signal[*click] = 0.5;
+    }
+    (signal, clicks)
+}
+
+#[test]
+fn stretching_steady_sine_keeps_level() {
+    let g = analyzer();
+    let input = sine(2 * SAMPLE_RATE);
+    for factor in [1.5, 0.5] {
+        let output = time_stretch_signal(&g, &input, factor);
+        assert_eq!(output.len(), (input.len() as f64 * factor).round() as usize);
+        for (i, db) in chunk_levels_db(inner(&output)).into_iter().enumerate() {
+            assert!(db.abs() < 1.0, "factor {}: chunk {} is {} dB off", factor, i + 3, db);
+        }
+    }
+}
+
+#[test]
+fn stretching_keeps_pitch() {
+    let g = analyzer();
+    let input = sine(2 * SAMPLE_RATE);
+    for factor in [1.5, 0.5, 2.0] {
+        let output = time_stretch_signal(&g, &input, factor);
+        let hz = frequency_hz(inner(&output));
+        assert!((hz / 440.0 - 1.0).abs() < 0.002, "factor {}: {} Hz", factor, hz);
+    }
+}
+
+#[test]
+fn stretched_clicks_stay_sharp() {
+    let g = analyzer();
+    let (input, clicks) = click_train(2 * SAMPLE_RATE);
+    for factor in [1.5, 0.75] {
+        let output = time_stretch_signal(&g, &input, factor);
+
+        // Without transient handling clicks are smeared by phase vocoder stretching
+        let mut coefs = Coefs::new(&g);
+        g.analyze(&input, 0, &mut coefs);
+        let params = StretchParams { transient_db: f32::INFINITY, ..StretchParams::default() };
+        let stretched = time_stretch_with(&g, &mut coefs, factor, &params);
+        let mut smeared = vec![0.0; output.len()];
+        g.synthesize(&stretched, 0, &mut smeared);
+
+        // Share of energy within 5 ms of a click, out of the energy within half the stretched click period around it,
+        // and the offset of the largest sample from the click's stretched time
+        let half_period = (SAMPLE_RATE as f64 / 8.0 * factor) as usize;
+        let sharpness = |signal: &[f32], click: usize| {
+            let t = (click as f64 * factor).round() as usize;
+            let around = &signal[t - half_period..t + half_period];
+            let energy = |s: &[f32]| s.iter().map(|x| x * x).sum::<f32>();
+            let near = energy(&around[half_period - 240..half_period + 240]) / energy(around);
+            let peak = (0..around.len()).max_by(|a, b| around[*a].abs().total_cmp(&around[*b].abs())).unwrap();
+            (near, peak as i64 - half_period as i64)
+        };
+        for click in &clicks {
+            let (near, offset) = sharpness(&output, *click);
+            assert!(near > 0.9, "factor {}: {} of energy near click at {}", factor, near, click);
+            assert!(offset.abs() <= 2, "factor {}: click at {} moved by {} samples", factor, click, offset);
+            let (smeared_near, _) = sharpness(&smeared, *click);
+            assert!(smeared_near < near - 0.1, "factor {}: no sharper than without transient handling", factor);
+        }
+    }
+}
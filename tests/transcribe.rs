@@ -0,0 +1,86 @@
+use std::io::ErrorKind;
+
+use gaborator::transcribe::{transcribe, NoteEvent, TranscribeParams};
+use gaborator::{Coefs, Gaborator, GaboratorParams};
+
+const SAMPLE_RATE: f64 = 48000.0;
+const LEN: usize = SAMPLE_RATE as usize;
+
+fn analyzer() -> Gaborator {
+    Gaborator::with_sample_rate(&GaboratorParams::for_sample_rate(SAMPLE_RATE, 20.0, 440.0, 24), SAMPLE_RATE)
+}
+
+/// Harmonic tone of `hz` with decaying harmonics, sounding within `range` of samples, in silence elsewhere
+fn tone(hz: f64, amplitude: f32, range: std::ops::Range<usize>) -> Vec<f32> {
+    (0..LEN)
+        .map(|i| {
+            if !range.contains(&i) {
+                return 0.0;
+            }
+            let phase = i as f64 * hz / SAMPLE_RATE * std::f64::consts::TAU;
+            (1..=4).map(|h| amplitude / h as f32 * (phase * h as f64).sin() as f32).sum()
+        })
+        .collect()
+}
+
+fn transcribed(signal: &[f32], params: &TranscribeParams) -> std::io::Result<Vec<NoteEvent>> {
+    let g = analyzer();
+    let mut coefs = Coefs::new(&g);
+    g.analyze(signal, 0, &mut coefs);
+    transcribe(&g, &mut coefs, 0..signal.len() as i64, params)
+}
+
+/// Frames are 512 samples apart and low bands are smeared in time by analysis
+fn assert_time_near(actual: i64, expected: usize) {
+    assert!((actual - expected as i64).abs() <= 2048, "time {} instead of {}", actual, expected);
+}
+
+#[test]
+fn synthetic_notes_are_transcribed() {
+    // A4, joined by a quieter C#5 (about -9.5 dB) while it sounds
+    let a4 = tone(440.0, 0.3, LEN / 4..LEN * 3 / 4);
+    let c5 = tone(440.0 * (4.0f64 / 12.0).exp2(), 0.1, LEN / 2..LEN * 7 / 8);
+    let signal: Vec<f32> = a4.iter().zip(&c5).map(|(a, b)| a + b).collect();
+
+    let notes = transcribed(&signal, &TranscribeParams::default()).unwrap();
+    assert_eq!(notes.len(), 2, "{:?}", notes);
+    for (n, (midi, onset, offset)) in notes.iter().zip([(69, LEN / 4, LEN * 3 / 4), (73, LEN / 2, LEN * 7 / 8)]) {
+        assert_eq!(n.note.midi, midi);
+        assert!(n.note.cents.abs() < 10.0, "{:?}", n);
+        assert_time_near(n.onset, onset);
+        assert_time_near(n.offset, offset);
+    }
+    assert_eq!(notes[0].velocity, 127);
+    assert!((70..100).contains(&notes[1].velocity), "velocity {}", notes[1].velocity);
+}
+
+#[test]
+fn restruck_note_starts_again() {
+    // A4 decaying by about 20 dB before it is struck again
+    let a4 = tone(440.0, 0.3, LEN / 4..LEN);
+    let signal: Vec<f32> = a4
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            let since_strike = if i >= LEN / 2 { i - LEN / 2 } else { i.saturating_sub(LEN / 4) };
+            x * (-(since_strike as f32) / 4800.0).exp()
+        })
+        .collect();
+
+    let notes = transcribed(&signal, &TranscribeParams::default()).unwrap();
+    assert_eq!(notes.len(), 2, "{:?}", notes);
+    assert!(notes.iter().all(|n| n.note.midi == 69), "{:?}", notes);
+    assert_time_near(notes[0].onset, LEN / 4);
+    assert_time_near(notes[1].onset, LEN / 2);
+    assert_eq!(notes[0].offset, notes[1].onset);
+}
+
+#[test]
+fn non_negative_on_threshold_is_rejected() {
+    let signal = tone(440.0, 0.3, LEN / 4..LEN * 3 / 4);
+    for on_threshold_db in [0.0, 3.0, f32::NAN] {
+        let params = TranscribeParams { on_threshold_db, ..TranscribeParams::default() };
+        let err = transcribed(&signal, &params).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput, "{} dB", on_threshold_db);
+    }
+}
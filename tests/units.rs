@@ -0,0 +1,65 @@
+use std::io::ErrorKind;
+
+use gaborator::{Coefs, Gaborator, GaboratorParams};
+
+const SAMPLE_RATE: f64 = 48000.0;
+
+fn params() -> GaboratorParams {
+    GaboratorParams::for_sample_rate(SAMPLE_RATE, 50.0, 440.0, 24)
+}
+
+fn analyzed(g: &Gaborator) -> Coefs {
+    let signal: Vec<f32> = (0..SAMPLE_RATE as usize / 2)
+        .map(|i| (i as f64 * 440.0 / SAMPLE_RATE * std::f64::consts::TAU).sin() as f32 * 0.5)
+        .collect();
+    let mut coefs = Coefs::new(g);
+    g.analyze(&signal, 0, &mut coefs);
+    coefs
+}
+
+#[test]
+fn accessors_need_sample_rate() {
+    let g = Gaborator::new(&params());
+    let band = g.band_ref();
+    assert_eq!(g.band_hz(band), None);
+    assert_eq!(g.band_for_hz(440.0), None);
+    assert_eq!(g.bands_for_hz_range(100.0..1000.0), None);
+    assert_eq!(g.seconds_to_sample_time(1.0), None);
+    assert_eq!(g.sample_time_to_seconds(48000), None);
+    assert_eq!(g.band_note(band), None);
+    assert_eq!(g.bands_are_note_aligned(), None);
+
+    let g = Gaborator::with_sample_rate(&params(), SAMPLE_RATE);
+    assert!((g.band_hz(band).unwrap() - 440.0).abs() < 1e-6);
+    assert_eq!(g.band_for_hz(440.0), Some(band));
+    assert_eq!(g.seconds_to_sample_time(1.0), Some(48000));
+    assert_eq!(g.sample_time_to_seconds(24000), Some(0.5));
+    assert_eq!(g.band_note(band).map(|n| n.midi), Some(69));
+    assert_eq!(g.bands_are_note_aligned(), Some(true));
+}
+
+#[test]
+fn analyses_report_unknown_sample_rate() {
+    let g = Gaborator::new(&params());
+    let mut coefs = analyzed(&g);
+    let range = 0..24000;
+
+    let errors = [
+        coefs.process_seconds_hz(&g, 0.0..0.5, 100.0..1000.0, |_, _| ()).err(),
+        gaborator::pitch::track_pitch(&g, &mut coefs, range.clone(), &Default::default()).err(),
+        gaborator::onset::detect_onsets(&g, &mut coefs, range.clone(), &Default::default()).err(),
+        gaborator::beat::detect_beats(&g, &mut coefs, range.clone(), &Default::default(), &Default::default()).err(),
+        gaborator::transcribe::transcribe(&g, &mut coefs, range.clone(), &Default::default()).err(),
+        gaborator::chroma::chroma(&g, &mut coefs, 1024, range.clone(), &Default::default()).err(),
+    ];
+    for (i, e) in errors.iter().enumerate() {
+        assert_eq!(e.as_ref().map(|e| e.kind()), Some(ErrorKind::InvalidInput), "analysis {}", i);
+    }
+
+    let g = Gaborator::with_sample_rate(&params(), SAMPLE_RATE);
+    let mut coefs = analyzed(&g);
+    let frames = gaborator::pitch::track_pitch(&g, &mut coefs, 4800..19200, &Default::default()).unwrap();
+    let voiced: Vec<_> = frames.iter().filter(|f| f.voiced).collect();
+    assert!(!voiced.is_empty());
+    assert!(voiced.iter().all(|f| (f.hz - 440.0).abs() < 5.0), "{:?}", voiced);
+}
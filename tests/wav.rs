@@ -0,0 +1,65 @@
+#![cfg(feature = "wav")]
+
+use gaborator::wav::{int_sample_scale, read_wav, write_wav, SampleFormat, WavAudio};
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("gaborator-test-{}-{}.wav", std::process::id(), name))
+}
+
+fn audio() -> WavAudio {
+    WavAudio {
+        sample_rate: 44100,
+        channels: vec![vec![0.0, 0.5, -0.5, 0.25, -1.0], vec![0.999, -0.125, 0.75, 2.0, -2.0]],
+    }
+}
+
+#[test]
+fn integer_scale_maps_full_scale_to_one() {
+    for bits in [8, 16, 24, 32] {
+        let full_scale = (1u64 << (bits - 1)) as f32;
+        assert_eq!(int_sample_scale(bits) * full_scale, 1.0, "{} bits", bits);
+    }
+}
+
+#[test]
+fn samples_roundtrip_at_each_bit_depth() {
+    let formats = [
+        (SampleFormat::Int8, 8),
+        (SampleFormat::Int16, 16),
+        (SampleFormat::Int24, 24),
+        (SampleFormat::Int32, 32),
+        (SampleFormat::Float32, 0),
+    ];
+    for (format, bits) in formats {
+        let path = temp_path(&format!("{:?}", format));
+        write_wav(&path, &audio(), format).unwrap();
+
+        // Integer samples as stored in the file: scaled to the bit depth, clipped to full scale
+        let raw = hound::WavReader::open(&path).unwrap();
+        assert_eq!(raw.spec().channels, 2);
+        if bits > 0 {
+            assert_eq!(raw.spec().bits_per_sample, bits);
+            let samples: Vec<i32> = raw.into_samples::<i32>().map(|s| s.unwrap()).collect();
+            let half = 1i64 << (bits - 2);
+            let max = (1i64 << (bits - 1)) - 1;
+            // Interleaved: frame 1 is (0.5, -0.125), frame 3 is (0.25, 2.0), frame 4 is (-1.0, -2.0)
+            assert_eq!(samples[2] as i64, half, "{:?} 0.5", format);
+            assert_eq!(samples[6] as i64, half / 2, "{:?} 0.25", format);
+            assert_eq!(samples[7] as i64, max, "{:?} clipped 2.0", format);
+            assert_eq!(samples[8] as i64, -max - 1, "{:?} -1.0", format);
+            assert_eq!(samples[9] as i64, -max - 1, "{:?} clipped -2.0", format);
+        }
+
+        let back = read_wav(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(back.sample_rate, 44100);
+        let tolerance = if bits > 0 { int_sample_scale(bits) } else { 0.0 };
+        for (written, read) in audio().channels.iter().zip(&back.channels) {
+            assert_eq!(read.len(), written.len());
+            for (w, r) in written.iter().zip(read) {
+                let expected = if bits > 0 { w.max(-1.0).min(1.0 - tolerance) } else { *w };
+                assert!((r - expected).abs() <= tolerance, "{:?}: wrote {}, read {}", format, w, r);
+            }
+        }
+    }
+}